@@ -1,23 +1,167 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
-use crate::spec::Node;
+use crate::spec::{Node, SymlinkPolicy};
 
-pub fn check_tree(base: &Path, nodes: &[Node], missing: &mut Vec<PathBuf>) {
+/// What is actually on disk where the spec wants a folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PathStatus {
+    Ok,
+    Missing,
+    /// A regular file (or a symlink to one).
+    NotADirectory,
+    BrokenSymlink,
+    /// A symlink that resolves to a directory.
+    Symlink,
+    PermissionDenied,
+}
+
+impl fmt::Display for PathStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PathStatus::Ok => "ok",
+            PathStatus::Missing => "missing",
+            PathStatus::NotADirectory => "not a directory",
+            PathStatus::BrokenSymlink => "broken symlink",
+            PathStatus::Symlink => "symlink",
+            PathStatus::PermissionDenied => "permission denied",
+        })
+    }
+}
+
+/// Classifies `path` without trusting `Path::exists`, which follows symlinks
+/// and treats files and folders alike.
+pub fn classify(path: &Path) -> PathStatus {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(err) => return error_status(&err),
+    };
+
+    if meta.file_type().is_symlink() {
+        return match fs::metadata(path) {
+            Ok(target) if target.is_dir() => PathStatus::Symlink,
+            Ok(_) => PathStatus::NotADirectory,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => PathStatus::PermissionDenied,
+            Err(_) => PathStatus::BrokenSymlink,
+        };
+    }
+    if !meta.is_dir() {
+        return PathStatus::NotADirectory;
+    }
+    match fs::read_dir(path) {
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => PathStatus::PermissionDenied,
+        _ => PathStatus::Ok,
+    }
+}
+
+fn error_status(err: &std::io::Error) -> PathStatus {
+    match err.kind() {
+        ErrorKind::PermissionDenied => PathStatus::PermissionDenied,
+        // A file in the middle of the path (`finance` is a file, so
+        // `finance/2026` cannot exist) is reported on the file itself.
+        _ => PathStatus::Missing,
+    }
+}
+
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct Findings {
+    pub missing: Vec<PathBuf>,
+    pub not_directory: Vec<PathBuf>,
+    pub broken_symlinks: Vec<PathBuf>,
+    /// Symlinks to directories where the node allows them.
+    pub symlinks: Vec<PathBuf>,
+    /// Symlinks to directories where the node has `"symlink": "deny"`.
+    pub denied_symlinks: Vec<PathBuf>,
+    pub permission_denied: Vec<PathBuf>,
+    /// Undeclared entries inside `strict` folders.
+    pub unexpected: Vec<PathBuf>,
+}
+
+impl Findings {
+    /// Records `path` under its status. Returns whether its children can be
+    /// checked.
+    pub fn record(&mut self, path: &Path, status: PathStatus, policy: SymlinkPolicy) -> bool {
+        let list = match status {
+            PathStatus::Ok => return true,
+            PathStatus::Missing => &mut self.missing,
+            PathStatus::NotADirectory => &mut self.not_directory,
+            PathStatus::BrokenSymlink => &mut self.broken_symlinks,
+            PathStatus::Symlink if policy == SymlinkPolicy::Deny => &mut self.denied_symlinks,
+            PathStatus::Symlink => &mut self.symlinks,
+            PathStatus::PermissionDenied => &mut self.permission_denied,
+        };
+        list.push(path.to_path_buf());
+        matches!(status, PathStatus::Missing | PathStatus::Symlink)
+    }
+
+    /// True when a required folder is missing or blocked by something else.
+    pub fn has_problems(&self) -> bool {
+        !(self.missing.is_empty()
+            && self.not_directory.is_empty()
+            && self.broken_symlinks.is_empty()
+            && self.denied_symlinks.is_empty()
+            && self.permission_denied.is_empty())
+    }
+}
+
+pub fn check_tree(base: &Path, nodes: &[Node], findings: &mut Findings) {
     for node in nodes {
         let path = base.join(&node.path);
-        if !path.exists() {
-            missing.push(path.clone());
+        let status = classify(&path);
+        if !findings.record(&path, status, node.symlink) {
+            continue;
+        }
+        if node.strict && status != PathStatus::Missing {
+            check_extra(&path, &node.children, &node.allow_extra, findings);
         }
 
         if !node.children.is_empty() {
-            check_tree(&path, &node.children, missing);
+            check_tree(&path, &node.children, findings);
         }
     }
 }
 
+/// Reports immediate children of `dir` that are neither declared in `nodes`
+/// nor matched by `allow_extra`. Hidden entries (`.DS_Store`, ...) are ignored.
+pub fn check_extra(dir: &Path, nodes: &[Node], allow_extra: &[String], findings: &mut Findings) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let allowed = glob_set(allow_extra);
+
+    let mut extra: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.starts_with('.')
+                && !nodes.iter().any(|node| node.path == name)
+                && !allowed.is_match(name.as_ref())
+        })
+        .map(|entry| entry.path())
+        .collect();
+    extra.sort();
+    findings.unexpected.extend(extra);
+}
+
+fn glob_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    // Patterns are validated when the spec is loaded.
+    for glob in patterns.iter().filter_map(|p| Glob::new(p).ok()) {
+        builder.add(glob);
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::check_tree;
+    use super::{Findings, PathStatus, check_tree, classify};
     use crate::spec::Node;
     use std::fs;
     use tempfile::tempdir;
@@ -35,19 +179,123 @@ mod tests {
                 children: vec![Node {
                     path: "child-missing".to_string(),
                     children: vec![],
+                    ..Node::default()
                 }],
+                ..Node::default()
             },
             Node {
                 path: "missing".to_string(),
                 children: vec![],
+                ..Node::default()
             },
         ];
 
-        let mut missing = Vec::new();
-        check_tree(base, &nodes, &mut missing);
+        let mut findings = Findings::default();
+        check_tree(base, &nodes, &mut findings);
 
+        let mut missing = findings.missing;
         missing.sort();
         let expected = vec![base.join("exists/child-missing"), base.join("missing")];
         assert_eq!(missing, expected);
     }
+
+    #[test]
+    fn check_tree_reports_unexpected_children_of_strict_nodes() {
+        let dir = tempdir().expect("tempdir");
+        let base = dir.path();
+
+        for path in [
+            "docs/finance",
+            "docs/Untitled folder 3",
+            "docs/keep-me",
+            "loose/x",
+        ] {
+            fs::create_dir_all(base.join(path)).expect("create dir");
+        }
+        fs::write(base.join("docs/.DS_Store"), "").expect("hidden file");
+        fs::write(base.join("docs/stray.pdf"), "").expect("stray file");
+
+        let nodes = vec![
+            Node {
+                path: "docs".to_string(),
+                children: vec![Node {
+                    path: "finance".to_string(),
+                    ..Node::default()
+                }],
+                strict: true,
+                allow_extra: vec!["keep-*".to_string()],
+                ..Node::default()
+            },
+            Node {
+                path: "loose".to_string(),
+                ..Node::default()
+            },
+        ];
+
+        let mut findings = Findings::default();
+        check_tree(base, &nodes, &mut findings);
+
+        assert!(findings.missing.is_empty());
+        assert_eq!(
+            findings.unexpected,
+            vec![
+                base.join("docs/Untitled folder 3"),
+                base.join("docs/stray.pdf")
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn check_tree_classifies_files_and_symlinks() {
+        use crate::spec::SymlinkPolicy;
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir().expect("tempdir");
+        let base = dir.path();
+
+        fs::create_dir_all(base.join("real")).expect("create dir");
+        fs::write(base.join("finance"), "not a folder").expect("write file");
+        symlink(base.join("nowhere"), base.join("broken")).expect("broken link");
+        symlink(base.join("real"), base.join("linked")).expect("dir link");
+        symlink(base.join("real"), base.join("pinned")).expect("dir link");
+
+        assert_eq!(classify(&base.join("real")), PathStatus::Ok);
+        assert_eq!(classify(&base.join("finance")), PathStatus::NotADirectory);
+        assert_eq!(classify(&base.join("broken")), PathStatus::BrokenSymlink);
+        assert_eq!(classify(&base.join("linked")), PathStatus::Symlink);
+        assert_eq!(classify(&base.join("finance/2026")), PathStatus::Missing);
+
+        let node = |path: &str| Node {
+            path: path.to_string(),
+            children: vec![Node {
+                path: "child".to_string(),
+                ..Node::default()
+            }],
+            ..Node::default()
+        };
+        let nodes = vec![
+            node("finance"),
+            node("broken"),
+            node("linked"),
+            Node {
+                symlink: SymlinkPolicy::Deny,
+                ..node("pinned")
+            },
+        ];
+
+        let mut findings = Findings::default();
+        check_tree(base, &nodes, &mut findings);
+
+        assert_eq!(findings.not_directory, vec![base.join("finance")]);
+        assert_eq!(findings.broken_symlinks, vec![base.join("broken")]);
+        assert_eq!(findings.symlinks, vec![base.join("linked")]);
+        assert_eq!(findings.denied_symlinks, vec![base.join("pinned")]);
+        // Children are only checked below folders that resolve to a directory.
+        assert_eq!(
+            findings.missing,
+            vec![base.join("linked/child"), base.join("pinned/child")]
+        );
+        assert!(findings.has_problems());
+    }
 }
@@ -1,4 +1,11 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::commands::tidy::DeleteMode;
+use crate::duration::parse_duration;
+use crate::output::OutputFormat;
+use crate::spec_loader::SpecFormat;
 
 #[derive(Parser)]
 #[command(
@@ -7,6 +14,14 @@ use clap::{Parser, Subcommand};
     about = "Personal system checker and organizer (macOS). Safe by default."
 )]
 pub struct Cli {
+    /// Spec file to use (overrides LIFE_OS_SPEC and the search path)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub spec: Option<PathBuf>,
+
+    /// Output format for doctor, init, tidy and dupes reports
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -37,9 +52,22 @@ pub enum Command {
         /// Perform actions (move/delete). Without this, runs in dry-run mode.
         #[arg(long)]
         apply: bool,
-        /// Delete all downloads (non-hidden), regardless of age
+        /// Ignore retention ages: clear every unmatched item from inboxes
+        /// that have one (Downloads by default)
         #[arg(long)]
         all: bool,
+        /// Where deleted downloads go
+        #[arg(long, value_enum, default_value_t = DeleteMode::Quarantine)]
+        delete_mode: DeleteMode,
+        /// Retention for inboxes that have one (overrides the spec; default 7d)
+        #[arg(long, value_name = "AGE", value_parser = parse_duration)]
+        older_than: Option<Duration>,
+        /// Only tidy this inbox (e.g. Downloads)
+        #[arg(long, value_name = "NAME")]
+        inbox: Option<String>,
+        /// Do not count folders on other filesystems in sizes
+        #[arg(long)]
+        one_file_system: bool,
         /// Show full details regardless of status
         #[arg(long)]
         verbose: bool,
@@ -47,6 +75,128 @@ pub enum Command {
         #[arg(long)]
         plain: bool,
     },
+
+    /// Find files with identical contents in an area
+    Dupes {
+        /// Area name from the spec (e.g. Documents)
+        area: String,
+        /// Also print each set's SHA-256
+        #[arg(long)]
+        verbose: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
+
+    /// Manage tidy's quarantine (System/life-os/quarantine)
+    Quarantine {
+        #[command(subcommand)]
+        command: QuarantineCommand,
+    },
+
+    /// Maintain the screenshot archive (Documents/screenshots)
+    Screenshots {
+        #[command(subcommand)]
+        command: ScreenshotsCommand,
+    },
+
+    /// Reverse a `tidy --apply` run using the journal in System/life-os/state
+    Undo {
+        /// Run to reverse (defaults to the latest run not yet undone)
+        run_id: Option<String>,
+        /// Move items back. Without this, runs in dry-run mode.
+        #[arg(long)]
+        apply: bool,
+        /// List every item, not just conflicts
+        #[arg(long)]
+        verbose: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
+
+    /// Print the JSON Schema of `--format json` output
+    Schema,
+
+    /// Inspect and maintain the spec file
+    Spec {
+        #[command(subcommand)]
+        command: SpecCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SpecCommand {
+    /// Check the spec for mistakes (exit code 1 when errors are found)
+    Lint {
+        /// Also fail on warnings
+        #[arg(long)]
+        deny_warnings: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
+
+    /// Upgrade the spec file to the latest format version
+    Migrate {
+        /// Rewrite the file on disk (a timestamped backup is kept)
+        #[arg(long)]
+        write: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
+
+    /// Write the spec in another format (json, toml or yaml)
+    Convert {
+        /// Target format
+        #[arg(long, value_enum)]
+        to: SpecFormat,
+        /// Output file (`-` for stdout). Defaults to the spec path with the new extension.
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum QuarantineCommand {
+    /// Permanently delete quarantine buckets older than a given age
+    Purge {
+        /// Age of buckets to delete, e.g. 30d, 2w, 12h
+        #[arg(long, value_name = "AGE", value_parser = parse_duration)]
+        older_than: Duration,
+        /// Delete the buckets. Without this, runs in dry-run mode.
+        #[arg(long)]
+        apply: bool,
+        /// Also list buckets that are kept
+        #[arg(long)]
+        verbose: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ScreenshotsCommand {
+    /// Move files lying directly in the archive into YYYY/MM folders
+    Reorganize {
+        /// Move the files. Without this, runs in dry-run mode.
+        #[arg(long)]
+        apply: bool,
+        /// List every move
+        #[arg(long)]
+        verbose: bool,
+        /// Disable colors and symbols
+        #[arg(long)]
+        plain: bool,
+    },
 }
 
 pub fn parse() -> Cli {
@@ -1,35 +1,68 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
-use crate::check::check_tree;
-use crate::spec::Node;
-use crate::spec_loader::{expand_root, load_spec};
+use crate::check::{Findings, PathStatus, check_extra, check_tree, classify};
+use crate::spec::{Node, SpecFile, SymlinkPolicy};
+use crate::spec_loader::{SpecLocation, expand_root, load_spec};
+use crate::user_dirs::{self, Source, UserDirs};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DoctorReport {
-    pub missing: Vec<PathBuf>,
+    pub findings: Findings,
     pub areas: usize,
     pub required: usize,
     pub roots: Vec<PathBuf>,
+    pub spec: SpecLocation,
+    pub folders: Vec<RequiredFolder>,
+    /// Spec paths that disagree with the XDG user dirs.
+    pub user_dirs: Vec<UserDirMismatch>,
 }
 
-pub fn run(_verbose: bool) -> Result<DoctorReport> {
+/// A required folder and the spec file that declared it.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RequiredFolder {
+    pub path: PathBuf,
+    pub source: Option<PathBuf>,
+}
+
+/// A spec path for a user dir that `user-dirs.dirs` or the environment
+/// puts elsewhere.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UserDirMismatch {
+    /// `tidy.downloads.path`, `areas.Documents.root`, ...
+    pub setting: String,
+    pub spec_path: PathBuf,
+    /// `XDG_DOWNLOAD_DIR`, ...
+    pub variable: String,
+    pub user_dir: PathBuf,
+    pub source: Source,
+}
+
+pub fn run(location: &SpecLocation, _verbose: bool) -> Result<DoctorReport> {
     let home = dirs::home_dir().context("could not determine home directory")?;
-    let spec = load_spec()?;
+    let spec = load_spec(location)?;
 
-    let mut missing: Vec<PathBuf> = Vec::new();
+    let mut findings = Findings::default();
     let mut roots: Vec<PathBuf> = Vec::new();
+    let mut folders: Vec<RequiredFolder> = Vec::new();
 
     for area in &spec.areas {
         let root = expand_root(&area.root, &home);
         roots.push(root.clone());
+        collect_folders(&root, &area.required, &mut folders);
 
-        if !root.exists() {
-            missing.push(root.clone());
+        // Roots may be symlinks (e.g. Documents synced from elsewhere).
+        let status = classify(&root);
+        if !findings.record(&root, status, SymlinkPolicy::Allow) || status == PathStatus::Missing {
             continue;
         }
 
-        check_tree(&root, &area.required, &mut missing);
+        if area.strict {
+            check_extra(&root, &area.required, &area.allow_extra, &mut findings);
+        }
+        check_tree(&root, &area.required, &mut findings);
     }
 
     let required = spec
@@ -39,13 +72,70 @@ pub fn run(_verbose: bool) -> Result<DoctorReport> {
         .sum();
 
     Ok(DoctorReport {
-        missing,
+        findings,
         areas: spec.areas.len(),
         required,
         roots,
+        spec: location.clone(),
+        folders,
+        user_dirs: user_dir_mismatches(&spec, &UserDirs::load(&home)),
     })
 }
 
+/// Tidy paths and area roots at the default location of a user dir that
+/// has been moved, or tidy paths that point elsewhere.
+fn user_dir_mismatches(spec: &SpecFile, dirs: &UserDirs) -> Vec<UserDirMismatch> {
+    let home = dirs.home();
+    let mut settings: Vec<(String, PathBuf, Option<&str>)> = Vec::new();
+    let tidy = [
+        ("desktop", &spec.tidy.desktop.path, "XDG_DESKTOP_DIR"),
+        ("downloads", &spec.tidy.downloads.path, "XDG_DOWNLOAD_DIR"),
+    ];
+    for (name, path, variable) in tidy {
+        if let Some(path) = path {
+            settings.push((
+                format!("tidy.{name}.path"),
+                expand_root(path, home),
+                Some(variable),
+            ));
+        }
+    }
+    for area in &spec.areas {
+        let root = expand_root(&area.root, home);
+        let variable = user_dirs::USER_DIRS
+            .iter()
+            .find(|(_, default, _)| root == home.join(default))
+            .map(|(variable, _, _)| *variable);
+        settings.push((format!("areas.{}.root", area.name), root, variable));
+    }
+
+    settings
+        .into_iter()
+        .filter_map(|(setting, spec_path, variable)| {
+            let variable = variable?;
+            let (user_dir, source) = dirs.configured(variable)?;
+            (spec_path != *user_dir).then(|| UserDirMismatch {
+                setting,
+                spec_path,
+                variable: variable.to_string(),
+                user_dir: user_dir.clone(),
+                source: *source,
+            })
+        })
+        .collect()
+}
+
+fn collect_folders(base: &Path, nodes: &[Node], out: &mut Vec<RequiredFolder>) {
+    for node in nodes {
+        let path = base.join(&node.path);
+        out.push(RequiredFolder {
+            path: path.clone(),
+            source: node.source.clone(),
+        });
+        collect_folders(&path, &node.children, out);
+    }
+}
+
 fn count_nodes(nodes: &[Node]) -> usize {
     let mut total = 0;
     for node in nodes {
@@ -59,19 +149,26 @@ fn count_nodes(nodes: &[Node]) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::run;
+    use super::{run, user_dir_mismatches};
+    use crate::spec::SpecFile;
+    use crate::spec_loader::{SpecLocation, SpecSource};
+    use crate::user_dirs::UserDirs;
     use std::fs;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
     use std::sync::{Mutex, OnceLock};
     use tempfile::tempdir;
 
     static ENV_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
 
-    fn write_spec(home: &Path, content: &str) {
+    fn write_spec(home: &Path, content: &str) -> SpecLocation {
         let spec_path = home.join("System/life-os/config/spec.json");
         let parent = spec_path.parent().expect("spec parent");
         fs::create_dir_all(parent).expect("create spec dir");
-        fs::write(spec_path, content).expect("write spec");
+        fs::write(&spec_path, content).expect("write spec");
+        SpecLocation {
+            path: spec_path,
+            source: SpecSource::Legacy,
+        }
     }
 
     fn with_temp_home<F: FnOnce(&Path)>(f: F) {
@@ -99,7 +196,7 @@ mod tests {
     #[test]
     fn doctor_returns_ok_when_spec_is_satisfied() {
         with_temp_home(|home| {
-            write_spec(
+            let location = write_spec(
                 home,
                 r#"{
   "version": 1,
@@ -235,15 +332,15 @@ mod tests {
                 fs::create_dir_all(home.join(path)).expect("create dir");
             }
 
-            let report = run(false).expect("doctor run");
-            assert!(report.missing.is_empty());
+            let report = run(&location, false).expect("doctor run");
+            assert!(report.findings.missing.is_empty());
         });
     }
 
     #[test]
     fn doctor_returns_failure_when_missing_folders() {
         with_temp_home(|home| {
-            write_spec(
+            let location = write_spec(
                 home,
                 r#"{
   "version": 1,
@@ -262,8 +359,144 @@ mod tests {
 
             fs::create_dir_all(home.join("Documents/archive")).expect("create archive");
 
-            let report = run(false).expect("doctor run");
-            assert_eq!(report.missing.len(), 1);
+            let report = run(&location, false).expect("doctor run");
+            assert_eq!(report.findings.missing.len(), 1);
+        });
+    }
+
+    #[test]
+    fn doctor_treats_compact_paths_like_nested_nodes() {
+        with_temp_home(|home| {
+            let location = write_spec(
+                home,
+                r#"{
+  "version": 1,
+  "areas": [
+    {
+      "name": "System",
+      "root": "~/System",
+      "required": [
+        "apps",
+        "life-os/repo",
+        "life-os/state",
+        { "path": "life-os", "children": ["repo"] }
+      ]
+    }
+  ]
+}"#,
+            );
+
+            fs::create_dir_all(home.join("System/life-os/repo")).expect("create repo");
+
+            let report = run(&location, false).expect("doctor run");
+            assert_eq!(report.required, 4);
+            assert_eq!(report.folders.len(), 4);
+            assert_eq!(
+                report.folders[0].source.as_deref(),
+                Some(location.path.as_path())
+            );
+            assert_eq!(
+                report.findings.missing,
+                vec![home.join("System/apps"), home.join("System/life-os/state")]
+            );
+        });
+    }
+
+    #[test]
+    fn doctor_reports_unexpected_items_in_strict_areas() {
+        with_temp_home(|home| {
+            let location = write_spec(
+                home,
+                r#"{
+  "version": 1,
+  "areas": [
+    {
+      "name": "Documents",
+      "root": "~/Documents",
+      "strict": true,
+      "allow_extra": ["*.localized"],
+      "required": ["finance", "school"]
+    }
+  ]
+}"#,
+            );
+
+            for path in ["finance", "school/extra", "Untitled folder 3"] {
+                fs::create_dir_all(home.join("Documents").join(path)).expect("create dir");
+            }
+            fs::write(home.join("Documents/.localized.localized"), "").expect("hidden");
+            fs::write(home.join("Documents/notes.localized"), "").expect("allowed");
+
+            let report = run(&location, false).expect("doctor run");
+            assert!(report.findings.missing.is_empty());
+            assert_eq!(
+                report.findings.unexpected,
+                vec![home.join("Documents/Untitled folder 3")]
+            );
         });
     }
+
+    #[test]
+    fn doctor_does_not_accept_a_file_as_a_folder() {
+        with_temp_home(|home| {
+            let location = write_spec(
+                home,
+                r#"{
+  "version": 1,
+  "areas": [
+    { "name": "Documents", "root": "~/Documents", "required": ["finance/2026", "school"] }
+  ]
+}"#,
+            );
+
+            fs::create_dir_all(home.join("Documents/school")).expect("create dir");
+            fs::write(home.join("Documents/finance"), "oops").expect("write file");
+
+            let report = run(&location, false).expect("doctor run");
+            assert!(report.findings.missing.is_empty());
+            assert_eq!(
+                report.findings.not_directory,
+                vec![home.join("Documents/finance")]
+            );
+            assert!(report.findings.has_problems());
+        });
+    }
+
+    #[test]
+    fn doctor_reports_missing_spec_file_as_error() {
+        let location = SpecLocation {
+            path: PathBuf::from("/nonexistent/life-os/spec.json"),
+            source: SpecSource::Flag,
+        };
+
+        let err = run(&location, false).expect_err("missing spec");
+        assert!(format!("{err:#}").contains("/nonexistent/life-os/spec.json"));
+    }
+
+    #[test]
+    fn doctor_reports_paths_that_disagree_with_user_dirs() {
+        let spec: SpecFile = serde_json::from_str(
+            r#"{
+  "version": 1,
+  "areas": [
+    { "name": "Documents", "root": "~/Documents" },
+    { "name": "Music", "root": "~/Music" }
+  ],
+  "tidy": { "desktop": { "path": "~/Desktop" }, "downloads": { "path": "~/inbox" } }
+}"#,
+        )
+        .expect("spec");
+        let home = Path::new("/home/u");
+        let dirs = UserDirs::resolve(
+            home,
+            Some("XDG_DESKTOP_DIR=\"$HOME/Työpöytä\"\nXDG_DOCUMENTS_DIR=\"$HOME/Asiakirjat\""),
+            |name| (name == "XDG_DOWNLOAD_DIR").then(|| "$HOME/inbox".to_string()),
+        );
+
+        let mismatches = user_dir_mismatches(&spec, &dirs);
+        let settings: Vec<_> = mismatches.iter().map(|m| m.setting.as_str()).collect();
+        assert_eq!(settings, ["tidy.desktop.path", "areas.Documents.root"]);
+        assert_eq!(mismatches[1].spec_path, home.join("Documents"));
+        assert_eq!(mismatches[1].user_dir, home.join("Asiakirjat"));
+    }
 }
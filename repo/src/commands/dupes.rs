@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::disk_usage;
+use crate::hash::file_hash;
+
+#[derive(Debug, Default, Clone, Serialize, JsonSchema)]
+pub struct DupesReport {
+    pub area: String,
+    pub root: PathBuf,
+    /// Regular files looked at (hidden entries and symlinks are skipped;
+    /// hard links to one file count once).
+    pub files: usize,
+    /// Largest waste first.
+    pub groups: Vec<DupeGroup>,
+    /// Bytes that would be freed by keeping one file per group.
+    pub wasted_bytes: u64,
+    /// Files or folders that could not be read.
+    pub unreadable: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct DupeGroup {
+    /// SHA-256 of the contents, hex.
+    pub hash: String,
+    /// Size of each copy.
+    pub size: u64,
+    /// Sorted; at least two.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Finds files with identical contents below `root`. Only files whose size
+/// matches another file's are hashed; empty files are ignored.
+pub fn run(area: &str, root: &Path) -> Result<DupesReport> {
+    let mut report = DupesReport {
+        area: area.to_string(),
+        root: root.to_path_buf(),
+        ..DupesReport::default()
+    };
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    walk(root, &mut report, &mut by_size, &mut HashSet::new())
+        .with_context(|| format!("failed to read directory: {}", root.display()))?;
+
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        let mut by_hash: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for path in paths {
+            match file_hash(&path) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(path),
+                Err(_) => report.unreadable.push(path),
+            }
+        }
+        for (hash, mut paths) in by_hash {
+            if paths.len() < 2 {
+                continue;
+            }
+            paths.sort();
+            report.wasted_bytes += size * (paths.len() as u64 - 1);
+            report.groups.push(DupeGroup { hash, size, paths });
+        }
+    }
+    report
+        .groups
+        .sort_by_key(|g| std::cmp::Reverse(g.size * (g.paths.len() as u64 - 1)));
+    report.unreadable.sort();
+    Ok(report)
+}
+
+fn walk(
+    dir: &Path,
+    report: &mut DupesReport,
+    by_size: &mut BTreeMap<u64, Vec<PathBuf>>,
+    inodes: &mut HashSet<(u64, u64)>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            report.unreadable.push(path);
+            continue;
+        };
+        if meta.is_dir() {
+            if walk(&path, report, by_size, inodes).is_err() {
+                report.unreadable.push(path);
+            }
+        } else if meta.is_file() {
+            // Another name for a file already seen: removing it frees nothing.
+            if disk_usage::link_count(&meta) > 1
+                && let Some(inode) = disk_usage::inode(&meta)
+                && !inodes.insert(inode)
+            {
+                continue;
+            }
+            report.files += 1;
+            if meta.len() > 0 {
+                by_size.entry(meta.len()).or_default().push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// True when `a` and `b` are both regular files with the same contents.
+pub fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+    let (Ok(meta_a), Ok(meta_b)) = (fs::symlink_metadata(a), fs::symlink_metadata(b)) else {
+        return Ok(false);
+    };
+    if !meta_a.is_file() || !meta_b.is_file() || meta_a.len() != meta_b.len() {
+        return Ok(false);
+    }
+    Ok(file_hash(a)? == file_hash(b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, same_contents};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn run_groups_identical_files_and_skips_hidden_and_empty_ones() {
+        let dir = tempdir().expect("tempdir");
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b")).expect("dirs");
+        fs::write(root.join("one.pdf"), "same contents").expect("write");
+        fs::write(root.join("a/b/one (1).pdf"), "same contents").expect("write");
+        fs::write(root.join("a/other.pdf"), "same length!!").expect("write");
+        fs::write(root.join(".hidden.pdf"), "same contents").expect("write");
+        fs::write(root.join("empty1"), "").expect("write");
+        fs::write(root.join("a/empty2"), "").expect("write");
+        fs::write(root.join("a/linked.pdf"), "hard linked").expect("write");
+        fs::hard_link(root.join("a/linked.pdf"), root.join("linked.pdf")).expect("hard link");
+
+        let report = run("Documents", root).expect("dupes");
+        assert_eq!(report.files, 6);
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(
+            report.groups[0].paths,
+            vec![root.join("a/b/one (1).pdf"), root.join("one.pdf")]
+        );
+        assert_eq!(report.wasted_bytes, 13);
+        assert!(same_contents(&root.join("one.pdf"), &root.join("a/b/one (1).pdf")).unwrap());
+        assert!(!same_contents(&root.join("one.pdf"), &root.join("a/other.pdf")).unwrap());
+        assert!(!same_contents(&root.join("one.pdf"), &root.join("missing")).unwrap());
+    }
+}
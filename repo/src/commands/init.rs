@@ -1,69 +1,98 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::spec::Node;
-use crate::spec_loader::{expand_root, load_spec};
+use crate::check::{PathStatus, classify};
+use crate::spec::{Node, SymlinkPolicy};
+use crate::spec_loader::{SpecLocation, expand_root, load_spec};
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize, JsonSchema)]
 pub struct InitReport {
     pub created: Vec<PathBuf>,
+    /// Required folders that init left alone because something else is in
+    /// the way (a file, a broken symlink, ...). Their children are skipped.
+    pub blocked: Vec<BlockedPath>,
 }
 
-pub fn run(verbose: bool) -> Result<InitReport> {
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct BlockedPath {
+    pub path: PathBuf,
+    pub status: PathStatus,
+}
+
+pub fn run(location: &SpecLocation, verbose: bool) -> Result<InitReport> {
     let home = dirs::home_dir().context("could not determine home directory")?;
-    let spec = load_spec()?;
+    let spec = load_spec(location)?;
 
-    let mut created: Vec<PathBuf> = Vec::new();
+    let mut report = InitReport::default();
 
     for area in &spec.areas {
         let root = expand_root(&area.root, &home);
 
         // Ensure root exists
-        ensure_dir(&root, verbose, &mut created)
+        let usable = ensure_dir(&root, SymlinkPolicy::Allow, verbose, &mut report)
             .with_context(|| format!("failed ensuring root for area {}", area.name))?;
 
         // Ensure all required nodes exist
-        ensure_tree(&root, &area.required, verbose, &mut created)?;
+        if usable {
+            ensure_tree(&root, &area.required, verbose, &mut report)?;
+        }
     }
 
-    Ok(InitReport { created })
+    Ok(report)
 }
 
-fn ensure_tree(
-    base: &Path,
-    nodes: &[Node],
-    verbose: bool,
-    created: &mut Vec<PathBuf>,
-) -> Result<()> {
+fn ensure_tree(base: &Path, nodes: &[Node], verbose: bool, report: &mut InitReport) -> Result<()> {
     for node in nodes {
         let path = base.join(&node.path);
-        ensure_dir(&path, verbose, created)?;
+        if !ensure_dir(&path, node.symlink, verbose, report)? {
+            continue;
+        }
 
         if !node.children.is_empty() {
-            ensure_tree(&path, &node.children, verbose, created)?;
+            ensure_tree(&path, &node.children, verbose, report)?;
         }
     }
     Ok(())
 }
 
-fn ensure_dir(path: &Path, verbose: bool, created: &mut Vec<PathBuf>) -> Result<()> {
-    if path.exists() {
-        return Ok(());
+/// Creates `path` if it is missing. Returns false (and records why) when
+/// something other than a usable directory is already there; that is never
+/// replaced.
+fn ensure_dir(
+    path: &Path,
+    symlink: SymlinkPolicy,
+    verbose: bool,
+    report: &mut InitReport,
+) -> Result<bool> {
+    match classify(path) {
+        PathStatus::Ok => return Ok(true),
+        PathStatus::Symlink if symlink == SymlinkPolicy::Allow => return Ok(true),
+        PathStatus::Missing => {}
+        status => {
+            report.blocked.push(BlockedPath {
+                path: path.to_path_buf(),
+                status,
+            });
+            return Ok(false);
+        }
     }
     fs::create_dir_all(path)
         .with_context(|| format!("failed to create directory: {}", path.display()))?;
-    created.push(path.to_path_buf());
+    report.created.push(path.to_path_buf());
     if verbose {
         println!("created: {}", path.display());
     }
-    Ok(())
+    Ok(true)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ensure_dir, ensure_tree};
-    use crate::spec::Node;
+    use super::{BlockedPath, InitReport, ensure_dir, ensure_tree};
+    use crate::check::PathStatus;
+    use crate::spec::{Node, SymlinkPolicy};
     use std::fs;
     use tempfile::tempdir;
 
@@ -78,19 +107,23 @@ mod tests {
                 Node {
                     path: "b".to_string(),
                     children: vec![],
+                    ..Node::default()
                 },
                 Node {
                     path: "c".to_string(),
                     children: vec![Node {
                         path: "d".to_string(),
                         children: vec![],
+                        ..Node::default()
                     }],
+                    ..Node::default()
                 },
             ],
+            ..Node::default()
         }];
 
-        let mut created = Vec::new();
-        ensure_tree(base, &nodes, false, &mut created).expect("ensure_tree");
+        let mut report = InitReport::default();
+        ensure_tree(base, &nodes, false, &mut report).expect("ensure_tree");
 
         assert!(base.join("a").is_dir());
         assert!(base.join("a/b").is_dir());
@@ -104,10 +137,47 @@ mod tests {
         let path = dir.path().join("exists");
         fs::create_dir_all(&path).expect("create dir");
 
-        let mut created = Vec::new();
-        ensure_dir(&path, false, &mut created).expect("ensure_dir");
+        let mut report = InitReport::default();
+        let usable =
+            ensure_dir(&path, SymlinkPolicy::Allow, false, &mut report).expect("ensure_dir");
 
+        assert!(usable);
         assert!(path.is_dir());
-        assert!(created.is_empty());
+        assert!(report.created.is_empty());
+    }
+
+    #[test]
+    fn ensure_tree_never_replaces_a_file() {
+        let dir = tempdir().expect("tempdir");
+        let base = dir.path();
+        fs::write(base.join("finance"), "keep me").expect("write file");
+
+        let nodes = vec![
+            Node {
+                path: "finance".to_string(),
+                children: vec![Node {
+                    path: "2026".to_string(),
+                    ..Node::default()
+                }],
+                ..Node::default()
+            },
+            Node {
+                path: "school".to_string(),
+                ..Node::default()
+            },
+        ];
+
+        let mut report = InitReport::default();
+        ensure_tree(base, &nodes, false, &mut report).expect("ensure_tree");
+
+        assert_eq!(fs::read_to_string(base.join("finance")).unwrap(), "keep me");
+        assert_eq!(
+            report.blocked,
+            vec![BlockedPath {
+                path: base.join("finance"),
+                status: PathStatus::NotADirectory
+            }]
+        );
+        assert_eq!(report.created, vec![base.join("school")]);
     }
 }
@@ -1,46 +1,241 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
 
-use crate::cli::{Cli, Command};
-use crate::commands::tidy::TidyOptions;
+use crate::cli::{Cli, Command, QuarantineCommand, ScreenshotsCommand, SpecCommand};
+use crate::commands::tidy::{DeleteMode, TidyOptions};
+use crate::disk_usage;
+use crate::journal;
+use crate::lint::Severity;
+use crate::output::{self, OutputFormat, Report, print_json};
+use crate::rules::RuleAction;
+use crate::spec::SpecFile;
+use crate::spec_loader::{expand_root, load_spec, locate_spec};
+use crate::user_dirs::UserDirs;
 
 pub mod doctor;
+pub mod dupes;
 pub mod init;
+pub mod quarantine;
+pub mod screenshots;
+pub mod spec;
 pub mod tidy;
+pub mod undo;
 
 pub fn dispatch(cli: Cli) -> Result<std::process::ExitCode> {
+    let json = cli.format == OutputFormat::Json;
+    if json
+        && !matches!(
+            cli.command,
+            Command::Doctor { .. }
+                | Command::Init { .. }
+                | Command::Tidy { .. }
+                | Command::Dupes { .. }
+        )
+    {
+        bail!("--format json is only supported by doctor, init, tidy and dupes");
+    }
+
     match cli.command {
         Command::Doctor { verbose, plain } => {
-            let report = doctor::run(verbose)?;
-            print_doctor(&report, OutputStyle::new(plain, verbose));
-            Ok(if report.missing.is_empty() {
-                std::process::ExitCode::from(0)
+            let location = locate_spec(cli.spec.as_deref())?;
+            let report = doctor::run(&location, verbose)?;
+            let code = if report.findings.has_problems() {
+                1
+            } else if !report.findings.unexpected.is_empty() {
+                3
+            } else {
+                0
+            };
+            if json {
+                print_json(Report::Doctor(&report), code)?;
             } else {
-                std::process::ExitCode::from(1)
-            })
+                print_doctor(&report, OutputStyle::new(plain, verbose));
+            }
+            Ok(std::process::ExitCode::from(code))
         }
         Command::Init { verbose, plain } => {
-            let report = init::run(verbose)?;
-            print_init(&report, OutputStyle::new(plain, verbose));
-            Ok(std::process::ExitCode::from(0))
+            let location = locate_spec(cli.spec.as_deref())?;
+            // Verbose init prints while it works, which would break JSON.
+            let report = init::run(&location, verbose && !json)?;
+            let code = if report.blocked.is_empty() { 0 } else { 1 };
+            if json {
+                print_json(Report::Init(&report), code)?;
+            } else {
+                print_init(&report, OutputStyle::new(plain, verbose));
+            }
+            Ok(std::process::ExitCode::from(code))
         }
         Command::Tidy {
             apply,
             all,
+            delete_mode,
+            older_than,
+            inbox,
+            one_file_system,
             verbose,
             plain,
         } => {
             let home = dirs::home_dir().context("could not determine home directory")?;
-            let options = TidyOptions {
+            let user_dirs = UserDirs::load(&home);
+            let mut options = TidyOptions {
                 apply,
-                delete_all_downloads: all,
-                desktop: home.join("Desktop"),
-                downloads: home.join("Downloads"),
-                screenshots_dest: home.join("Documents/screenshots"),
+                ignore_age: all,
+                delete_mode,
+                ..TidyOptions::new(&user_dirs)
             };
+            if let Some((spec, path)) = optional_spec(cli.spec.as_deref())? {
+                options
+                    .configure(&spec, &user_dirs)
+                    .with_context(|| format!("invalid spec: {}", path.display()))?;
+            }
+            if let Some(age) = older_than {
+                options.override_older_than(age);
+            }
+            options.one_filesystem |= one_file_system;
+            if let Some(name) = &inbox {
+                options.select_inbox(name)?;
+            }
             let report = tidy::run(&options)?;
-            print_tidy(&report, OutputStyle::new(plain, verbose), apply, all);
+            let code = if report.failures().next().is_some() {
+                4
+            } else {
+                0
+            };
+            if json {
+                print_json(Report::Tidy(&report), code)?;
+            } else {
+                print_tidy(&report, OutputStyle::new(plain, verbose), all);
+            }
+            Ok(std::process::ExitCode::from(code))
+        }
+        Command::Dupes {
+            area,
+            verbose,
+            plain,
+        } => {
+            let home = dirs::home_dir().context("could not determine home directory")?;
+            let location = locate_spec(cli.spec.as_deref())?;
+            let spec = load_spec(&location)?;
+            let Some(found) = spec
+                .areas
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(&area))
+            else {
+                let known: Vec<_> = spec.areas.iter().map(|a| a.name.as_str()).collect();
+                bail!("unknown area `{area}` (known: {})", known.join(", "));
+            };
+            let root = expand_root(&found.root, &home);
+            let report = dupes::run(&found.name, &root)?;
+            if json {
+                print_json(Report::Dupes(&report), 0)?;
+            } else {
+                print_dupes(&report, OutputStyle::new(plain, verbose));
+            }
+            Ok(std::process::ExitCode::from(0))
+        }
+        Command::Quarantine {
+            command:
+                QuarantineCommand::Purge {
+                    older_than,
+                    apply,
+                    verbose,
+                    plain,
+                },
+        } => {
+            let home = dirs::home_dir().context("could not determine home directory")?;
+            let root = quarantine::default_root(&home);
+            let report = quarantine::purge(&root, older_than, chrono::Local::now(), apply)?;
+            print_purge(&report, OutputStyle::new(plain, verbose));
+            Ok(std::process::ExitCode::from(0))
+        }
+        Command::Screenshots {
+            command:
+                ScreenshotsCommand::Reorganize {
+                    apply,
+                    verbose,
+                    plain,
+                },
+        } => {
+            let home = dirs::home_dir().context("could not determine home directory")?;
+            let desktop = optional_spec(cli.spec.as_deref())?
+                .map(|(spec, _)| spec.tidy.desktop)
+                .unwrap_or_default();
+            let root = tidy::screenshot_folder(&desktop, &UserDirs::load(&home));
+            let report = screenshots::reorganize(&root, &journal::default_path(&home), apply)?;
+            print_reorganize(&report, OutputStyle::new(plain, verbose));
+            let code = if report.failed.is_empty() { 0 } else { 4 };
+            Ok(std::process::ExitCode::from(code))
+        }
+        Command::Undo {
+            run_id,
+            apply,
+            verbose,
+            plain,
+        } => {
+            let home = dirs::home_dir().context("could not determine home directory")?;
+            let report = undo::run(&journal::default_path(&home), run_id.as_deref(), apply)?;
+            print_undo(&report, OutputStyle::new(plain, verbose));
+            let code = if !report.failed.is_empty() {
+                4
+            } else if !report.conflicts.is_empty() {
+                1
+            } else {
+                0
+            };
+            Ok(std::process::ExitCode::from(code))
+        }
+        Command::Schema => {
+            println!("{}", output::schema()?);
             Ok(std::process::ExitCode::from(0))
         }
+        Command::Spec {
+            command:
+                SpecCommand::Lint {
+                    deny_warnings,
+                    plain,
+                },
+        } => {
+            let location = locate_spec(cli.spec.as_deref())?;
+            let report = spec::lint(&location)?;
+            print_lint(&report, OutputStyle::new(plain, false));
+            let failed = report.count(Severity::Error) > 0
+                || (deny_warnings && report.count(Severity::Warning) > 0);
+            Ok(std::process::ExitCode::from(if failed { 1 } else { 0 }))
+        }
+        Command::Spec {
+            command: SpecCommand::Migrate { write, plain },
+        } => {
+            let location = locate_spec(cli.spec.as_deref())?;
+            let report = spec::migrate_file(&location, write)?;
+            print_migrate(&report, OutputStyle::new(plain, false));
+            Ok(std::process::ExitCode::from(0))
+        }
+        Command::Spec {
+            command:
+                SpecCommand::Convert {
+                    to,
+                    output,
+                    force,
+                    plain,
+                },
+        } => {
+            let location = locate_spec(cli.spec.as_deref())?;
+            let report = spec::convert_file(&location, to, output.as_deref(), force)?;
+            if report.output.is_some() {
+                print_convert(&report, OutputStyle::new(plain, false));
+            }
+            Ok(std::process::ExitCode::from(0))
+        }
+    }
+}
+
+/// The spec for commands that also work without one (tidy, screenshots):
+/// `None` when no spec is found and `--spec` was not given.
+fn optional_spec(flag: Option<&Path>) -> Result<Option<(SpecFile, PathBuf)>> {
+    match locate_spec(flag) {
+        Ok(location) => Ok(Some((load_spec(&location)?, location.path))),
+        Err(_) if flag.is_none() => Ok(None),
+        Err(err) => Err(err),
     }
 }
 
@@ -71,6 +266,10 @@ impl OutputStyle {
         if self.plain { "ERROR" } else { "✗" }
     }
 
+    fn warn_symbol(&self) -> &'static str {
+        if self.plain { "WARN" } else { "!" }
+    }
+
     fn section(&self, text: &str) -> String {
         if self.plain {
             text.to_string()
@@ -98,7 +297,8 @@ impl OutputStyle {
 
 fn print_doctor(report: &doctor::DoctorReport, style: OutputStyle) {
     println!("{}", style.header("life-os doctor"));
-    if report.missing.is_empty() {
+    let findings = &report.findings;
+    if !findings.has_problems() && findings.unexpected.is_empty() {
         let msg = format!(
             "{} Spec satisfied ({} areas, {} folders)",
             style.ok_symbol(),
@@ -106,38 +306,128 @@ fn print_doctor(report: &doctor::DoctorReport, style: OutputStyle) {
             report.required
         );
         println!("{}", color_if(style, &msg, Color::Success));
-    } else {
+    }
+
+    let problems = [
+        ("Missing folders", "Missing", &findings.missing),
+        (
+            "Not a directory",
+            "Not a directory",
+            &findings.not_directory,
+        ),
+        (
+            "Broken symlinks",
+            "Broken symlinks",
+            &findings.broken_symlinks,
+        ),
+        (
+            "Disallowed symlinks",
+            "Disallowed symlinks",
+            &findings.denied_symlinks,
+        ),
+        (
+            "Permission denied",
+            "Permission denied",
+            &findings.permission_denied,
+        ),
+    ];
+    for (summary, _, paths) in &problems {
+        if !paths.is_empty() {
+            let msg = format!("{} {} ({})", style.err_symbol(), summary, paths.len());
+            println!("{}", color_if(style, &msg, Color::Error));
+        }
+    }
+    if !findings.unexpected.is_empty() {
         let msg = format!(
-            "{} Missing folders ({})",
-            style.err_symbol(),
-            report.missing.len()
+            "{} Unexpected items ({})",
+            style.warn_symbol(),
+            findings.unexpected.len()
         );
-        println!("{}", color_if(style, &msg, Color::Error));
+        println!("{}", color_if(style, &msg, Color::Warning));
+    }
+    if !report.user_dirs.is_empty() {
+        let msg = format!(
+            "{} Spec disagrees with XDG user dirs ({})",
+            style.warn_symbol(),
+            report.user_dirs.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Warning));
+    }
+
+    let mut sections: Vec<(&str, &Vec<std::path::PathBuf>)> = problems
+        .iter()
+        .map(|(_, section, paths)| (*section, *paths))
+        .collect();
+    sections.push(("Unexpected", &findings.unexpected));
+    if style.verbose {
+        sections.push(("Symlinked", &findings.symlinks));
+    }
+    for (section, paths) in sections {
+        if paths.is_empty() {
+            continue;
+        }
         println!();
-        println!("Missing");
-        for path in &report.missing {
+        println!("{section}");
+        for path in paths {
             println!("{} {}", bullet(style), path.display());
         }
     }
+    if !report.user_dirs.is_empty() {
+        println!();
+        println!("User dirs");
+        for mismatch in &report.user_dirs {
+            println!(
+                "{} {} is {}, but {} is {} {}",
+                bullet(style),
+                mismatch.setting,
+                mismatch.spec_path.display(),
+                mismatch.variable,
+                mismatch.user_dir.display(),
+                style.dim(&format!("(from {})", mismatch.source.as_str()))
+            );
+        }
+    }
 
     if style.verbose {
+        println!();
+        println!("Spec");
+        println!(
+            "{} {} {}",
+            bullet(style),
+            report.spec.path.display(),
+            style.dim(&format!("(via {})", report.spec.source))
+        );
         println!();
         println!("Roots");
         for root in &report.roots {
             println!("{} {}", bullet(style), root.display());
         }
+        println!();
+        println!("Folders");
+        for folder in &report.folders {
+            let source = match &folder.source {
+                Some(source) => format!("(from {})", source.display()),
+                None => String::new(),
+            };
+            println!(
+                "{} {} {}",
+                bullet(style),
+                folder.path.display(),
+                style.dim(&source)
+            );
+        }
     }
 }
 
 fn print_init(report: &init::InitReport, style: OutputStyle) {
     println!("{}", style.header("life-os init"));
-    if report.created.is_empty() {
+    if report.created.is_empty() && report.blocked.is_empty() {
         let msg = format!(
             "{} Nothing to create (spec already satisfied)",
             style.ok_symbol()
         );
         println!("{}", color_if(style, &msg, Color::Success));
-    } else {
+    } else if !report.created.is_empty() {
         let msg = format!(
             "{} Created {} folder(s)",
             style.ok_symbol(),
@@ -152,170 +442,377 @@ fn print_init(report: &init::InitReport, style: OutputStyle) {
             }
         }
     }
-}
 
-fn print_tidy(report: &tidy::TidyReport, style: OutputStyle, apply: bool, delete_all: bool) {
-    println!("{}", style.header("life-os tidy"));
+    if !report.blocked.is_empty() {
+        let msg = format!(
+            "{} Skipped {} path(s) that are not plain folders",
+            style.err_symbol(),
+            report.blocked.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Error));
+        println!();
+        println!("Blocked");
+        for init::BlockedPath { path, status } in &report.blocked {
+            println!(
+                "{} {} {}",
+                bullet(style),
+                path.display(),
+                style.dim(&format!("({status})"))
+            );
+        }
+    }
+}
 
-    let desktop_clean = report.desktop_screenshots.len() <= 10 && report.desktop_other.len() <= 2;
-    let mut downloads_level = downloads_level(report.downloads_total_bytes);
-    if report.downloads_items.len() > 100 {
-        downloads_level = downloads_level.bump();
+fn print_lint(report: &spec::LintReport, style: OutputStyle) {
+    println!("{}", style.header("life-os spec lint"));
+    let errors = report.count(Severity::Error);
+    let warnings = report.count(Severity::Warning);
+    if report.diagnostics.is_empty() {
+        let msg = format!(
+            "{} No problems in {}",
+            style.ok_symbol(),
+            report.spec.path.display()
+        );
+        println!("{}", color_if(style, &msg, Color::Success));
+        return;
     }
 
-    let summary = format!(
-        "{} Desktop {}, Downloads {}",
-        style.ok_symbol(),
-        if desktop_clean { "clean" } else { "busy" },
-        downloads_level.as_str()
+    let msg = format!(
+        "{} {} error(s), {} warning(s)",
+        if errors > 0 {
+            style.err_symbol()
+        } else {
+            style.ok_symbol()
+        },
+        errors,
+        warnings
     );
-    println!("{}", color_if(style, &summary, Color::Success));
+    let kind = if errors > 0 {
+        Color::Error
+    } else {
+        Color::Success
+    };
+    println!("{}", color_if(style, &msg, kind));
+    println!();
+    for diagnostic in &report.diagnostics {
+        let location = match diagnostic.pos {
+            Some(pos) => format!("{}:{}", report.spec.path.display(), pos),
+            None => report.spec.path.display().to_string(),
+        };
+        let label = format!("{}[{}]", diagnostic.severity, diagnostic.code);
+        let label = match diagnostic.severity {
+            Severity::Error => color_if(style, &label, Color::Error),
+            Severity::Warning => style.highlight(&label),
+        };
+        println!("{}: {}: {}", location, label, diagnostic.message);
+    }
+}
 
-    let show_full = style.verbose || !desktop_clean || !downloads_level.is_light();
-    if !show_full {
-        println!();
-        println!("{}", style.section("Desktop"));
+fn print_migrate(report: &spec::MigrateReport, style: OutputStyle) {
+    println!("{}", style.header("life-os spec migrate"));
+    let msg = if report.from == report.to {
+        format!(
+            "{} Spec already at version {}",
+            style.ok_symbol(),
+            report.to
+        )
+    } else if report.written {
+        format!(
+            "{} Migrated spec from version {} to {}",
+            style.ok_symbol(),
+            report.from,
+            report.to
+        )
+    } else {
+        format!(
+            "{} Spec is at version {}; run with --write to migrate to {}",
+            style.ok_symbol(),
+            report.from,
+            report.to
+        )
+    };
+    println!("{}", color_if(style, &msg, Color::Success));
+    println!("{} {}", bullet(style), report.spec.path.display());
+    if let Some(backup) = &report.backup {
         println!(
-            "{} Screenshots: {} ({})",
+            "{} {}",
             bullet(style),
-            style.highlight(&report.desktop_screenshots.len().to_string()),
-            style.dim(&tidy::human_bytes(total_size(&report.desktop_screenshots)))
+            style.dim(&format!("backup: {}", backup.display()))
         );
+    }
+}
+
+fn print_convert(report: &spec::ConvertReport, style: OutputStyle) {
+    println!("{}", style.header("life-os spec convert"));
+    let msg = format!(
+        "{} Converted spec to {}",
+        style.ok_symbol(),
+        report.to.extension()
+    );
+    println!("{}", color_if(style, &msg, Color::Success));
+    println!("{} {}", bullet(style), report.spec.path.display());
+    if let Some(output) = &report.output {
         println!(
-            "{} Other files: {} ({})",
+            "{} {}",
             bullet(style),
-            style.highlight(&report.desktop_other.len().to_string()),
-            style.dim(&tidy::human_bytes(total_size(&report.desktop_other)))
+            style.dim(&format!("written: {}", output.display()))
         );
-        println!();
-        println!("{}", style.section("Downloads"));
-        println!(
-            "{} Items: {} ({})",
-            bullet(style),
-            style.highlight(&report.downloads_items.len().to_string()),
-            style.dim(&tidy::human_bytes(report.downloads_total_bytes))
+    }
+}
+
+fn print_tidy(report: &tidy::TidyReport, style: OutputStyle, delete_all: bool) {
+    println!("{}", style.header("life-os tidy"));
+
+    let statuses: Vec<String> = report
+        .inboxes
+        .iter()
+        .map(|inbox| format!("{} {}", inbox.name, inbox.status()))
+        .collect();
+    let failures: Vec<_> = report.failures().collect();
+    let failed_inboxes = report
+        .inboxes
+        .iter()
+        .filter(|inbox| inbox.error.is_some())
+        .count();
+    let failed_items = failures.len() - failed_inboxes;
+    let (symbol, colour) = if failures.is_empty() {
+        (style.ok_symbol(), Color::Success)
+    } else {
+        (style.err_symbol(), Color::Error)
+    };
+    let summary = format!("{symbol} {}", statuses.join(", "));
+    println!("{}", color_if(style, &summary, colour));
+    if failed_inboxes > 0 {
+        let msg = format!("{} {failed_inboxes} inbox(es) failed", style.err_symbol());
+        println!("{}", color_if(style, &msg, Color::Error));
+    }
+    if failed_items > 0 {
+        let msg = format!("{} {failed_items} item(s) failed", style.err_symbol());
+        println!("{}", color_if(style, &msg, Color::Error));
+    }
+    let unjournaled: Vec<_> = report.unjournaled().collect();
+    if !unjournaled.is_empty() {
+        let msg = format!(
+            "{} {} item(s) not journaled; `undo` cannot reverse them",
+            style.warn_symbol(),
+            unjournaled.len()
         );
-        println!(
-            "{} Old (>7 days): {} ({})",
-            bullet(style),
-            style.highlight(&report.downloads_old_items.len().to_string()),
-            style.dim(&tidy::human_bytes(report.downloads_old_bytes))
+        println!("{}", color_if(style, &msg, Color::Warning));
+    }
+    let unlisted: Vec<_> = report.unlisted().collect();
+    if !unlisted.is_empty() {
+        let msg = format!(
+            "{} {} quarantined item(s) missing from the manifest",
+            style.warn_symbol(),
+            unlisted.len()
         );
-        if apply {
-            println!();
-            println!("{}", style.section("Actions"));
+        println!("{}", color_if(style, &msg, Color::Warning));
+    }
+
+    let show_full = style.verbose || report.inboxes.iter().any(|inbox| inbox.busy);
+    for inbox in &report.inboxes {
+        println!();
+        println!("{}", style.section(&inbox.name));
+        if let Some(error) = &inbox.error {
+            println!("{} {}", bullet(style), color_if(style, error, Color::Error));
+            continue;
+        }
+        if inbox.absent {
             println!(
-                "{} Moved screenshots: {}",
+                "{} {}",
                 bullet(style),
-                style.highlight(&report.planned_moves.len().to_string())
+                style.dim(&format!("absent ({} does not exist)", inbox.path.display()))
             );
-            if delete_all {
-                println!(
-                    "{} Deleted downloads (all): {}",
-                    bullet(style),
-                    style.highlight(&report.planned_downloads_deletions.len().to_string())
-                );
-            } else {
+            continue;
+        }
+        println!(
+            "{} Items: {} ({})",
+            bullet(style),
+            style.highlight(&inbox.items.len().to_string()),
+            style.dim(&sizes(inbox))
+        );
+        if show_full {
+            for path in &inbox.items {
+                let size = disk_usage::size(path);
                 println!(
-                    "{} Deleted downloads (>7 days): {}",
+                    "{} {} ({})",
                     bullet(style),
-                    style.highlight(&report.planned_downloads_deletions.len().to_string())
+                    path.display(),
+                    style.dim(&tidy::human_bytes(size))
                 );
             }
         }
-        return;
+        if let Some(age) = &inbox.older_than {
+            println!(
+                "{} Old (>{}): {} ({})",
+                bullet(style),
+                age,
+                style.highlight(&inbox.old_items.len().to_string()),
+                style.dim(&tidy::human_bytes(inbox.old_bytes))
+            );
+        }
+        if report.applied {
+            print_inbox_actions(inbox, style);
+        } else {
+            print_inbox_plan(inbox, report.delete_mode, style, delete_all);
+        }
+        for skipped in &inbox.skipped {
+            let reason = match skipped.reason {
+                tidy::SkipReason::InProgress => "download in progress".to_string(),
+                tidy::SkipReason::Changed => "changed since the scan".to_string(),
+                tidy::SkipReason::TooNew => format!("modified in the last {}", report.grace_period),
+            };
+            println!(
+                "{} {}: skipped {}",
+                bullet(style),
+                file_name(&skipped.path),
+                style.dim(&format!("({reason})"))
+            );
+        }
     }
 
-    println!();
-    println!("{}", style.section("Desktop"));
-    println!(
-        "{} Screenshots: {} ({})",
-        bullet(style),
-        style.highlight(&report.desktop_screenshots.len().to_string()),
-        style.dim(&tidy::human_bytes(total_size(&report.desktop_screenshots)))
-    );
-    for path in &report.desktop_screenshots {
-        let size = tidy::dir_or_file_size(path);
+    if !failures.is_empty() {
+        println!();
+        println!("{}", style.section("Failures"));
+        for (path, error) in &failures {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+    if !unjournaled.is_empty() {
+        println!();
+        println!("{}", style.section("Not journaled"));
+        for (path, error) in &unjournaled {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+    if !unlisted.is_empty() {
+        println!();
+        println!("{}", style.section("Not in manifest"));
+        for (path, error) in &unlisted {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+
+    if let Some(run_id) = &report.run_id {
+        println!();
         println!(
-            "{} {} ({})",
+            "{} {}",
             bullet(style),
-            path.display(),
-            style.dim(&tidy::human_bytes(size))
+            style.dim(&format!(
+                "run {run_id} (reverse with `life-os undo {run_id}`)"
+            ))
         );
     }
-    println!(
-        "{} Other files: {} ({})",
-        bullet(style),
-        style.highlight(&report.desktop_other.len().to_string()),
-        style.dim(&tidy::human_bytes(total_size(&report.desktop_other)))
-    );
-    for path in &report.desktop_other {
-        let size = tidy::dir_or_file_size(path);
+    let quarantined = report.inboxes.iter().flat_map(|i| &i.quarantined).next();
+    if let Some(entry) = quarantined
+        && let Some(bucket) = entry.stored.parent()
+    {
         println!(
-            "{} {} ({})",
+            "{} {}",
             bullet(style),
-            path.display(),
-            style.dim(&tidy::human_bytes(size))
+            style.dim(&format!("quarantined items are in {}", bucket.display()))
         );
     }
+}
 
-    println!();
-    println!("{}", style.section("Downloads"));
-    println!(
-        "{} Items: {} ({})",
-        bullet(style),
-        style.highlight(&report.downloads_items.len().to_string()),
-        style.dim(&tidy::human_bytes(report.downloads_total_bytes))
-    );
-    for path in &report.downloads_items {
-        let size = tidy::dir_or_file_size(path);
+fn print_inbox_plan(
+    inbox: &tidy::InboxReport,
+    delete_mode: DeleteMode,
+    style: OutputStyle,
+    delete_all: bool,
+) {
+    for planned in &inbox.planned {
+        let name = file_name(&planned.path);
+        let action = match (planned.action, &planned.destination) {
+            (RuleAction::MoveTo, Some(dest)) => format!("move to {}", dest.display()),
+            (RuleAction::Quarantine, _) if planned.duplicate_of.is_some() => {
+                "quarantine (duplicate)".to_string()
+            }
+            (RuleAction::Delete, _) => match delete_mode {
+                DeleteMode::Quarantine => "delete (to quarantine)".to_string(),
+                DeleteMode::Trash => "delete (to trash)".to_string(),
+                DeleteMode::Permanent => "delete permanently".to_string(),
+            },
+            (action, _) => action.as_str().to_string(),
+        };
+        let reason = match (&planned.duplicate_of, &planned.rule, &inbox.older_than) {
+            (Some(existing), _, _) => format!("identical to {}", existing.display()),
+            (None, Some(rule), _) => format!("rule {rule}"),
+            (None, None, _) if delete_all => "--all".to_string(),
+            (None, None, Some(age)) => format!("older than {age}"),
+            (None, None, None) => "no rule matched".to_string(),
+        };
         println!(
-            "{} {} ({})",
+            "{} {}: {} {}",
             bullet(style),
-            path.display(),
-            style.dim(&tidy::human_bytes(size))
+            name,
+            action,
+            style.dim(&format!("({reason})"))
         );
     }
-    println!(
-        "{} Old (>7 days): {} ({})",
-        bullet(style),
-        style.highlight(&report.downloads_old_items.len().to_string()),
-        style.dim(&tidy::human_bytes(report.downloads_old_bytes))
-    );
+}
 
-    if apply {
-        println!();
-        println!("{}", style.section("Actions"));
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// `1.2 GB`, plus the space on disk when that differs noticeably.
+fn sizes(inbox: &tidy::InboxReport) -> String {
+    let (apparent, allocated) = (inbox.total_bytes, inbox.allocated_bytes);
+    if tidy::human_bytes(apparent) == tidy::human_bytes(allocated) {
+        tidy::human_bytes(apparent)
+    } else {
+        format!(
+            "{}, {} on disk",
+            tidy::human_bytes(apparent),
+            tidy::human_bytes(allocated)
+        )
+    }
+}
+
+fn print_inbox_actions(inbox: &tidy::InboxReport, style: OutputStyle) {
+    let outcomes = |f: fn(&tidy::Outcome) -> bool| {
+        inbox
+            .planned
+            .iter()
+            .filter(|p| p.outcome.as_ref().is_some_and(f))
+            .count()
+    };
+    let counts = [
+        ("Moved", inbox.moved.len()),
+        ("Quarantined", inbox.quarantined.len()),
+        ("Trashed", inbox.trashed.len()),
+        ("Deleted", inbox.deleted.len()),
+        (
+            "Skipped",
+            outcomes(|o| matches!(o, tidy::Outcome::Skipped { .. })),
+        ),
+        (
+            "Failed",
+            outcomes(|o| matches!(o, tidy::Outcome::Failed { .. })),
+        ),
+    ];
+    if counts.iter().all(|(_, count)| *count == 0) {
+        println!("{} {}", bullet(style), style.dim("nothing to do"));
+    }
+    for (verb, count) in counts.into_iter().filter(|(_, count)| *count > 0) {
         println!(
-            "{} Moved screenshots: {}",
+            "{} {}: {}",
             bullet(style),
-            style.highlight(&report.planned_moves.len().to_string())
+            verb,
+            style.highlight(&count.to_string())
         );
-        if delete_all {
-            println!(
-                "{} Deleted downloads (all): {}",
-                bullet(style),
-                style.highlight(&report.planned_downloads_deletions.len().to_string())
-            );
-        } else {
-            println!(
-                "{} Deleted downloads (>7 days): {}",
-                bullet(style),
-                style.highlight(&report.planned_downloads_deletions.len().to_string())
-            );
-        }
     }
 }
 
-fn total_size(paths: &[std::path::PathBuf]) -> u64 {
-    paths.iter().map(|p| tidy::dir_or_file_size(p)).sum()
-}
-
 #[derive(Clone, Copy)]
 enum Color {
     Accent,
     Success,
     Error,
+    Warning,
     Dim,
 }
 
@@ -332,6 +829,7 @@ fn color(text: &str, color_kind: Color) -> String {
         Color::Accent => "36",
         Color::Success => "32",
         Color::Error => "31",
+        Color::Warning => "33",
         Color::Dim => "2",
     };
     format!("\u{1b}[{}m{}\u{1b}[0m", code, text)
@@ -341,42 +839,283 @@ fn bullet(style: OutputStyle) -> &'static str {
     if style.plain { "-" } else { "•" }
 }
 
-#[derive(Clone, Copy)]
-enum DownloadsLevel {
-    Light,
-    Moderate,
-    Heavy,
+fn print_dupes(report: &dupes::DupesReport, style: OutputStyle) {
+    println!("{}", style.header("life-os dupes"));
+    if report.groups.is_empty() {
+        let msg = format!(
+            "{} No duplicates in {} ({} file(s))",
+            style.ok_symbol(),
+            report.root.display(),
+            report.files
+        );
+        println!("{}", color_if(style, &msg, Color::Success));
+    } else {
+        let msg = format!(
+            "{} {} set(s) of duplicates in {}, {} wasted",
+            style.warn_symbol(),
+            report.groups.len(),
+            report.root.display(),
+            tidy::human_bytes(report.wasted_bytes)
+        );
+        println!("{}", color_if(style, &msg, Color::Warning));
+        for group in &report.groups {
+            println!();
+            println!(
+                "{} {}",
+                style.section(&tidy::human_bytes(group.size)),
+                style.dim(&format!("({} copies)", group.paths.len()))
+            );
+            for path in &group.paths {
+                let shown = path.strip_prefix(&report.root).unwrap_or(path);
+                println!("{} {}", bullet(style), shown.display());
+            }
+            if style.verbose {
+                println!("  {}", style.dim(&format!("sha256 {}", group.hash)));
+            }
+        }
+    }
+    if !report.unreadable.is_empty() {
+        println!();
+        println!("{}", style.section("Unreadable"));
+        for path in &report.unreadable {
+            println!("{} {}", bullet(style), path.display());
+        }
+    }
 }
 
-impl DownloadsLevel {
-    fn as_str(self) -> &'static str {
-        match self {
-            DownloadsLevel::Light => "light",
-            DownloadsLevel::Moderate => "moderate",
-            DownloadsLevel::Heavy => "heavy",
+fn print_purge(report: &quarantine::PurgeReport, style: OutputStyle) {
+    println!("{}", style.header("life-os quarantine purge"));
+    if report.expired.is_empty() {
+        let msg = format!(
+            "{} Nothing to purge in {}",
+            style.ok_symbol(),
+            report.root.display()
+        );
+        println!("{}", color_if(style, &msg, Color::Success));
+    } else {
+        let bytes = report.expired.iter().map(|b| b.bytes).sum();
+        let msg = format!(
+            "{} {} {} bucket(s) ({})",
+            style.ok_symbol(),
+            if report.applied {
+                "Purged"
+            } else {
+                "Would purge"
+            },
+            report.expired.len(),
+            tidy::human_bytes(bytes)
+        );
+        println!("{}", color_if(style, &msg, Color::Success));
+        println!();
+        println!("{}", style.section("Expired"));
+        for bucket in &report.expired {
+            println!(
+                "{} {} {}",
+                bullet(style),
+                bucket.path.display(),
+                style.dim(&format!(
+                    "({} item(s), {})",
+                    bucket.items,
+                    tidy::human_bytes(bucket.bytes)
+                ))
+            );
+        }
+        if !report.applied {
+            println!();
+            println!("{}", style.dim("Dry run: re-run with --apply to delete."));
         }
     }
 
-    fn bump(self) -> Self {
-        match self {
-            DownloadsLevel::Light => DownloadsLevel::Moderate,
-            DownloadsLevel::Moderate => DownloadsLevel::Heavy,
-            DownloadsLevel::Heavy => DownloadsLevel::Heavy,
+    if style.verbose && !report.kept.is_empty() {
+        println!();
+        println!("{}", style.section("Kept"));
+        for bucket in &report.kept {
+            println!(
+                "{} {} {}",
+                bullet(style),
+                bucket.path.display(),
+                style.dim(&format!(
+                    "({} item(s), {})",
+                    bucket.items,
+                    tidy::human_bytes(bucket.bytes)
+                ))
+            );
         }
     }
+}
+
+fn print_reorganize(report: &screenshots::ReorganizeReport, style: OutputStyle) {
+    println!("{}", style.header("life-os screenshots reorganize"));
+    if report.moves.is_empty() && report.failed.is_empty() {
+        let msg = format!(
+            "{} Nothing to move in {}",
+            style.ok_symbol(),
+            report.root.display()
+        );
+        println!("{}", color_if(style, &msg, Color::Success));
+        return;
+    }
+    let (symbol, colour) = if report.failed.is_empty() {
+        (style.ok_symbol(), Color::Success)
+    } else {
+        (style.err_symbol(), Color::Error)
+    };
+    let msg = format!(
+        "{} {} {} file(s) into YYYY/MM folders in {}",
+        symbol,
+        if report.applied {
+            "Moved"
+        } else {
+            "Would move"
+        },
+        report.moves.len(),
+        report.root.display()
+    );
+    println!("{}", color_if(style, &msg, colour));
+    if !report.failed.is_empty() {
+        let msg = format!(
+            "{} {} file(s) failed",
+            style.err_symbol(),
+            report.failed.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Error));
+    }
+    if !report.unjournaled.is_empty() {
+        let msg = format!(
+            "{} {} file(s) not journaled; `undo` cannot reverse them",
+            style.warn_symbol(),
+            report.unjournaled.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Warning));
+    }
 
-    fn is_light(self) -> bool {
-        matches!(self, DownloadsLevel::Light)
+    if style.verbose {
+        println!();
+        println!("{}", style.section("Moves"));
+        for planned in &report.moves {
+            let to = planned.to.strip_prefix(&report.root).unwrap_or(&planned.to);
+            println!(
+                "{} {} -> {}",
+                bullet(style),
+                planned.from.display(),
+                to.display()
+            );
+        }
+    }
+    if !report.failed.is_empty() {
+        println!();
+        println!("{}", style.section("Failures"));
+        for (path, error) in &report.failed {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+    if !report.unjournaled.is_empty() {
+        println!();
+        println!("{}", style.section("Not journaled"));
+        for (path, error) in &report.unjournaled {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+    println!();
+    match &report.run_id {
+        Some(run_id) => println!(
+            "{}",
+            style.dim(&format!(
+                "run {run_id} (reverse with `life-os undo {run_id}`)"
+            ))
+        ),
+        None => println!(
+            "{}",
+            style.dim("Dry run: re-run with --apply to move files.")
+        ),
     }
 }
 
-fn downloads_level(total_bytes: u64) -> DownloadsLevel {
-    const GB: u64 = 1024 * 1024 * 1024;
-    if total_bytes <= 1 * GB {
-        DownloadsLevel::Light
-    } else if total_bytes <= 5 * GB {
-        DownloadsLevel::Moderate
-    } else {
-        DownloadsLevel::Heavy
+fn print_undo(report: &undo::UndoReport, style: OutputStyle) {
+    println!("{}", style.header("life-os undo"));
+    let msg = format!(
+        "{} {} {} item(s) from run {}",
+        style.ok_symbol(),
+        if report.applied {
+            "Restored"
+        } else {
+            "Would restore"
+        },
+        report.restored.len(),
+        report.run_id
+    );
+    println!("{}", color_if(style, &msg, Color::Success));
+    if !report.conflicts.is_empty() {
+        let msg = format!(
+            "{} {} item(s) could not be restored",
+            style.err_symbol(),
+            report.conflicts.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Error));
+        println!();
+        println!("{}", style.section("Conflicts"));
+        for conflict in &report.conflicts {
+            let reason = match conflict.reason {
+                undo::ConflictReason::Reoccupied => "original location is in use",
+                undo::ConflictReason::Gone => "moved item no longer exists",
+                undo::ConflictReason::Deleted => "deleted permanently",
+            };
+            println!(
+                "{} {} {}",
+                bullet(style),
+                conflict.path.display(),
+                style.dim(&format!("({reason})"))
+            );
+        }
+    }
+    if !report.failed.is_empty() {
+        let msg = format!(
+            "{} {} item(s) failed",
+            style.err_symbol(),
+            report.failed.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Error));
+        println!();
+        println!("{}", style.section("Failures"));
+        for (path, error) in &report.failed {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+    if !report.unjournaled.is_empty() {
+        let msg = format!(
+            "{} {} restored item(s) not journaled",
+            style.warn_symbol(),
+            report.unjournaled.len()
+        );
+        println!("{}", color_if(style, &msg, Color::Warning));
+        println!();
+        println!("{}", style.section("Not journaled"));
+        for (path, error) in &report.unjournaled {
+            println!("{} {}: {}", bullet(style), path.display(), error);
+        }
+    }
+
+    if style.verbose {
+        if !report.restored.is_empty() {
+            println!();
+            println!("{}", style.section("Restored"));
+            for (from, to) in &report.restored {
+                println!("{} {} -> {}", bullet(style), from.display(), to.display());
+            }
+        }
+        if !report.already_restored.is_empty() {
+            println!();
+            println!("{}", style.section("Already restored"));
+            for path in &report.already_restored {
+                println!("{} {}", bullet(style), path.display());
+            }
+        }
+    }
+    if !report.applied && !report.restored.is_empty() {
+        println!();
+        println!(
+            "{}",
+            style.dim("Dry run: re-run with --apply to move items back.")
+        );
     }
 }
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::commands::tidy::unique_destination;
+use crate::disk_usage;
+use crate::transfer;
+
+/// One line per quarantined item in `<bucket>/manifest.jsonl`.
+pub const MANIFEST_FILE: &str = "manifest.jsonl";
+
+pub fn default_root(home: &Path) -> PathBuf {
+    home.join("System/life-os/quarantine")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ManifestEntry {
+    /// Where the item was before it was quarantined.
+    pub original: PathBuf,
+    /// Where it is now, inside the dated bucket.
+    pub stored: PathBuf,
+    pub size: u64,
+    /// RFC 3339 modification time of the original item.
+    pub modified: Option<String>,
+    /// RFC 3339 time the item was moved.
+    pub quarantined_at: String,
+}
+
+/// Moves `path` into `<root>/<YYYY-MM-DD>/` and appends it to that bucket's
+/// manifest. Name clashes inside a bucket get a ` (n)` suffix.
+///
+/// Once the item has moved the entry is returned whatever happens to the
+/// manifest, together with the result of writing it.
+pub fn store(
+    root: &Path,
+    path: &Path,
+    now: DateTime<Local>,
+) -> Result<(ManifestEntry, Result<()>)> {
+    let bucket = root.join(now.format("%Y-%m-%d").to_string());
+    fs::create_dir_all(&bucket)
+        .with_context(|| format!("failed to create quarantine bucket: {}", bucket.display()))?;
+
+    let name = path
+        .file_name()
+        .with_context(|| format!("cannot quarantine {}", path.display()))?
+        .to_string_lossy();
+    let stored = unique_destination(&bucket, &name);
+    let size = disk_usage::size(path);
+    let modified = fs::symlink_metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .map(|time| DateTime::<Local>::from(time).to_rfc3339());
+
+    transfer::move_path(path, &stored)
+        .with_context(|| format!("failed to quarantine {}", path.display()))?;
+
+    let entry = ManifestEntry {
+        original: path.to_path_buf(),
+        stored,
+        size,
+        modified,
+        quarantined_at: now.to_rfc3339(),
+    };
+    let written = append_manifest(&bucket.join(MANIFEST_FILE), &entry);
+    Ok((entry, written))
+}
+
+fn append_manifest(manifest: &Path, entry: &ManifestEntry) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(manifest)
+        .with_context(|| format!("failed to open manifest: {}", manifest.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("failed to write manifest: {}", manifest.display()))
+}
+
+#[derive(Debug)]
+pub struct PurgeReport {
+    pub root: PathBuf,
+    pub applied: bool,
+    /// Buckets older than the cutoff (removed when `applied`).
+    pub expired: Vec<Bucket>,
+    pub kept: Vec<Bucket>,
+}
+
+#[derive(Debug)]
+pub struct Bucket {
+    pub path: PathBuf,
+    pub date: NaiveDate,
+    pub items: usize,
+    pub bytes: u64,
+}
+
+/// Finds dated buckets older than `older_than` and deletes them when
+/// `apply` is set. Folders that are not `YYYY-MM-DD` are left alone.
+pub fn purge(
+    root: &Path,
+    older_than: Duration,
+    now: DateTime<Local>,
+    apply: bool,
+) -> Result<PurgeReport> {
+    let mut report = PurgeReport {
+        root: root.to_path_buf(),
+        applied: apply,
+        expired: Vec::new(),
+        kept: Vec::new(),
+    };
+    if !root.exists() {
+        return Ok(report);
+    }
+
+    // An age reaching back before the calendar starts: nothing has expired.
+    let cutoff = chrono::Duration::from_std(older_than)
+        .ok()
+        .and_then(|age| now.checked_sub_signed(age))
+        .map_or(NaiveDate::MIN, |cutoff| cutoff.date_naive());
+    let entries = fs::read_dir(root)
+        .with_context(|| format!("failed to read directory: {}", root.display()))?;
+    let mut buckets: Vec<Bucket> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let date = NaiveDate::parse_from_str(name.to_str()?, "%Y-%m-%d").ok()?;
+            Some(bucket_info(entry.path(), date))
+        })
+        .collect();
+    buckets.sort_by_key(|bucket| bucket.date);
+
+    for bucket in buckets {
+        if bucket.date < cutoff {
+            if apply {
+                fs::remove_dir_all(&bucket.path).with_context(|| {
+                    format!("failed to purge bucket: {}", bucket.path.display())
+                })?;
+            }
+            report.expired.push(bucket);
+        } else {
+            report.kept.push(bucket);
+        }
+    }
+
+    Ok(report)
+}
+
+fn bucket_info(path: PathBuf, date: NaiveDate) -> Bucket {
+    let items = fs::read_dir(&path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name() != MANIFEST_FILE)
+                .count()
+        })
+        .unwrap_or(0);
+    let bytes = disk_usage::size(&path);
+    Bucket {
+        path,
+        date,
+        items,
+        bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MANIFEST_FILE, purge, store};
+    use chrono::{Local, TimeZone};
+    use std::fs;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn store_moves_item_into_dated_bucket_and_writes_manifest() {
+        let dir = tempdir().expect("tempdir");
+        let root = dir.path().join("quarantine");
+        let downloads = dir.path().join("Downloads");
+        fs::create_dir_all(downloads.join("folder")).expect("downloads");
+        fs::write(downloads.join("a.zip"), "12345").expect("file");
+        fs::write(downloads.join("folder/b.txt"), "xy").expect("nested");
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap();
+
+        let (first, written) = store(&root, &downloads.join("a.zip"), now).expect("store file");
+        written.expect("manifest");
+        fs::write(downloads.join("a.zip"), "again").expect("file");
+        let (second, _) = store(&root, &downloads.join("a.zip"), now).expect("store again");
+        let (folder, _) = store(&root, &downloads.join("folder"), now).expect("store dir");
+
+        let bucket = root.join("2026-10-16");
+        assert_eq!(first.stored, bucket.join("a.zip"));
+        assert_eq!(first.size, 5);
+        assert_eq!(second.stored, bucket.join("a (1).zip"));
+        assert_eq!(folder.size, 2);
+        assert!(bucket.join("folder/b.txt").exists());
+        assert!(!downloads.join("a.zip").exists());
+
+        let manifest = fs::read_to_string(bucket.join(MANIFEST_FILE)).expect("manifest");
+        let lines: Vec<serde_json::Value> = manifest
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json line"))
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0]["original"],
+            downloads.join("a.zip").display().to_string()
+        );
+        assert!(lines[0]["modified"].is_string());
+    }
+
+    #[test]
+    fn store_keeps_the_moved_item_when_the_manifest_cannot_be_written() {
+        let dir = tempdir().expect("tempdir");
+        let root = dir.path().join("quarantine");
+        let bucket = root.join("2026-10-16");
+        fs::create_dir_all(bucket.join(MANIFEST_FILE)).expect("manifest in the way");
+        fs::write(dir.path().join("a.zip"), "12345").expect("file");
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap();
+
+        let (entry, written) = store(&root, &dir.path().join("a.zip"), now).expect("store");
+        assert!(format!("{:#}", written.unwrap_err()).contains("failed to open manifest"));
+        assert_eq!(entry.stored, bucket.join("a.zip"));
+        assert!(entry.stored.exists());
+        assert!(!dir.path().join("a.zip").exists());
+    }
+
+    #[test]
+    fn purge_removes_only_expired_dated_buckets() {
+        let dir = tempdir().expect("tempdir");
+        let root = dir.path();
+        for name in ["2026-09-01", "2026-09-20", "2026-10-15", "keep-me"] {
+            fs::create_dir_all(root.join(name)).expect("bucket");
+        }
+        fs::write(root.join("2026-09-01/old.zip"), "1234").expect("file");
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let month = Duration::from_secs(30 * 24 * 60 * 60);
+
+        let dry = purge(root, month, now, false).expect("dry run");
+        assert_eq!(dry.expired.len(), 1);
+        assert_eq!(dry.expired[0].items, 1);
+        assert_eq!(dry.expired[0].bytes, 4);
+        assert_eq!(dry.kept.len(), 2);
+        assert!(root.join("2026-09-01").exists());
+
+        purge(root, month, now, true).expect("purge");
+        assert!(!root.join("2026-09-01").exists());
+        assert!(root.join("2026-09-20").exists());
+        assert!(root.join("keep-me").exists());
+
+        let forever = Duration::from_secs(100_000_000 * 24 * 60 * 60);
+        let report = purge(root, forever, now, true).expect("huge age");
+        assert!(report.expired.is_empty());
+        assert_eq!(report.kept.len(), 2);
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::tidy::{PlannedMove, unique_destination};
+use crate::disk_usage;
+use crate::journal::{Journal, Operation};
+use crate::screenshots;
+use crate::transfer;
+
+#[derive(Debug)]
+pub struct ReorganizeReport {
+    pub root: PathBuf,
+    pub applied: bool,
+    /// Journal run ID of an applied run (`life-os undo <run_id>`).
+    pub run_id: Option<String>,
+    /// Files directly in `root`, each with its `YYYY/MM` destination.
+    pub moves: Vec<PlannedMove>,
+    /// Files that could not be moved, with the error. They stay in `root`.
+    pub failed: Vec<(PathBuf, String)>,
+    /// Moved files missing from the journal, with the error.
+    pub unjournaled: Vec<(PathBuf, String)>,
+}
+
+/// Moves the files lying directly in the screenshot folder `root` into its
+/// `YYYY/MM` buckets. Folders and hidden files stay where they are. A file
+/// that fails to move is recorded and the rest are still moved.
+pub fn reorganize(root: &Path, journal_path: &Path, apply: bool) -> Result<ReorganizeReport> {
+    let mut report = ReorganizeReport {
+        root: root.to_path_buf(),
+        applied: apply,
+        run_id: None,
+        moves: Vec::new(),
+        failed: Vec::new(),
+        unjournaled: Vec::new(),
+    };
+    let mut files = Vec::new();
+    for entry in fs::read_dir(root)
+        .with_context(|| format!("failed to read directory: {}", root.display()))?
+    {
+        let entry = entry.context("failed to read directory entry")?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type().is_ok_and(|t| t.is_file()) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+
+    let journal = apply.then(|| Journal::start(journal_path, Local::now()));
+    report.run_id = journal.as_ref().map(|j| j.run_id().to_string());
+    for path in files {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let bucket = root.join(screenshots::bucket(&path));
+        let dest = unique_destination(&bucket, name);
+        if let Some(journal) = &journal {
+            let size = match move_file(&path, &bucket, &dest) {
+                Ok(size) => size,
+                Err(err) => {
+                    report.failed.push((path, format!("{err:#}")));
+                    continue;
+                }
+            };
+            if let Err(err) = journal.record(Operation::Move, &path, Some(&dest), size, None) {
+                report.unjournaled.push((path.clone(), format!("{err:#}")));
+            }
+        }
+        report.moves.push(PlannedMove {
+            from: path,
+            to: dest,
+        });
+    }
+    Ok(report)
+}
+
+/// Moves `path` to `dest` inside `bucket`, returning its size.
+fn move_file(path: &Path, bucket: &Path, dest: &Path) -> Result<u64> {
+    if fs::symlink_metadata(dest).is_ok() {
+        bail!("destination appeared during the run: {}", dest.display());
+    }
+    fs::create_dir_all(bucket)
+        .with_context(|| format!("failed to create directory: {}", bucket.display()))?;
+    let size = disk_usage::size(path);
+    transfer::move_path(path, dest)?;
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reorganize;
+    use crate::journal;
+    use filetime::{FileTime, set_file_mtime};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn reorganize_buckets_flat_files_by_name_date_or_mtime() {
+        let dir = tempdir().expect("tempdir");
+        let root = dir.path().join("screenshots");
+        let journal_path = dir.path().join("journal.jsonl");
+        fs::create_dir_all(root.join("2026/10")).expect("bucket");
+        for name in [
+            "Screenshot 2026-10-16 at 10.00.00.png",
+            "Screenshot (4).png",
+            ".DS_Store",
+        ] {
+            fs::write(root.join(name), "new").expect("write");
+        }
+        fs::write(
+            root.join("2026/10/Screenshot 2026-10-16 at 10.00.00.png"),
+            "old",
+        )
+        .expect("write");
+        // 2023-07-15 12:00 UTC
+        set_file_mtime(
+            root.join("Screenshot (4).png"),
+            FileTime::from_unix_time(1_689_422_400, 0),
+        )
+        .expect("mtime");
+
+        let dry = reorganize(&root, &journal_path, false).expect("dry run");
+        assert_eq!(dry.moves.len(), 2);
+        assert!(root.join("Screenshot (4).png").exists());
+        assert!(!journal_path.exists());
+
+        let report = reorganize(&root, &journal_path, true).expect("apply");
+        assert!(root.join("2023/07/Screenshot (4).png").exists());
+        assert!(
+            root.join("2026/10/Screenshot 2026-10-16 at 10.00.00 (1).png")
+                .exists()
+        );
+        assert!(root.join(".DS_Store").exists());
+        let entries = journal::read(&journal_path).expect("journal");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(Some(&entries[0].run_id), report.run_id.as_ref());
+
+        let again = reorganize(&root, &journal_path, true).expect("second run");
+        assert!(again.moves.is_empty());
+    }
+
+    #[test]
+    fn reorganize_keeps_going_after_a_file_fails() {
+        let dir = tempdir().expect("tempdir");
+        let root = dir.path().join("screenshots");
+        let journal_path = dir.path().join("journal.jsonl");
+        // A file where the `2024/03` bucket should be.
+        fs::create_dir_all(root.join("2024")).expect("year");
+        fs::write(root.join("2024/03"), "").expect("blocker");
+        for name in [
+            "Screenshot from 2024-03-05 10-00-00.png",
+            "Screenshot from 2026-10-16 10-00-00.png",
+        ] {
+            fs::write(root.join(name), "x").expect("write");
+        }
+
+        let report = reorganize(&root, &journal_path, true).expect("apply");
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(
+            report.failed[0].0,
+            root.join("Screenshot from 2024-03-05 10-00-00.png")
+        );
+        assert!(report.failed[0].1.contains("failed to create directory"));
+        assert!(
+            root.join("Screenshot from 2024-03-05 10-00-00.png")
+                .exists()
+        );
+        assert_eq!(report.moves.len(), 1);
+        assert!(
+            root.join("2026/10/Screenshot from 2026-10-16 10-00-00.png")
+                .exists()
+        );
+        assert!(report.unjournaled.is_empty());
+        assert_eq!(journal::read(&journal_path).expect("journal").len(), 1);
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result, bail, ensure};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lint::{Diagnostic, Severity, lint_spec};
+use crate::spec::CURRENT_VERSION;
+use crate::spec_loader::{SpecFormat, SpecLocation, migrate, parse_spec};
+
+#[derive(Debug)]
+pub struct LintReport {
+    pub spec: SpecLocation,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+pub fn lint(location: &SpecLocation) -> Result<LintReport> {
+    let raw = fs::read_to_string(&location.path)
+        .with_context(|| format!("failed to read spec file: {}", location.path.display()))?;
+    let format = SpecFormat::from_path(&location.path)?;
+    let home = dirs::home_dir();
+
+    Ok(LintReport {
+        spec: location.clone(),
+        diagnostics: lint_spec(&raw, format, home.as_deref()),
+    })
+}
+
+#[derive(Debug)]
+pub struct MigrateReport {
+    pub spec: SpecLocation,
+    pub from: u32,
+    pub to: u32,
+    pub written: bool,
+    pub backup: Option<PathBuf>,
+}
+
+pub fn migrate_file(location: &SpecLocation, write: bool) -> Result<MigrateReport> {
+    let path = &location.path;
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read spec file: {}", path.display()))?;
+    let format = SpecFormat::from_path(path)?;
+    let mut doc: Value = format
+        .parse(&raw)
+        .with_context(|| format!("failed to parse spec file: {}", path.display()))?;
+    let from = migrate(&mut doc)?;
+
+    let mut report = MigrateReport {
+        spec: location.clone(),
+        from,
+        to: CURRENT_VERSION,
+        written: false,
+        backup: None,
+    };
+    if !write || from == CURRENT_VERSION {
+        return Ok(report);
+    }
+
+    let backup = backup_path(path, chrono::Local::now());
+    fs::copy(path, &backup)
+        .with_context(|| format!("failed to back up spec to {}", backup.display()))?;
+
+    let out = format.serialize(&doc).context("failed to serialize spec")?;
+    fs::write(path, out).with_context(|| format!("failed to write spec: {}", path.display()))?;
+
+    report.written = true;
+    report.backup = Some(backup);
+    Ok(report)
+}
+
+#[derive(Debug)]
+pub struct ConvertReport {
+    pub spec: SpecLocation,
+    pub to: SpecFormat,
+    /// `None` when the converted spec was printed to stdout.
+    pub output: Option<PathBuf>,
+}
+
+/// Converts the spec document to another format. The output is parsed back
+/// and compared with the input so nothing is silently dropped.
+pub fn convert_file(
+    location: &SpecLocation,
+    to: SpecFormat,
+    output: Option<&Path>,
+    force: bool,
+) -> Result<ConvertReport> {
+    let path = &location.path;
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read spec file: {}", path.display()))?;
+    let from = SpecFormat::from_path(path)?;
+    parse_spec(&raw, from)
+        .with_context(|| format!("failed to parse spec file: {}", path.display()))?;
+
+    let doc: Value = from.parse(&raw)?;
+    let out = to
+        .serialize(&doc)
+        .with_context(|| format!("failed to convert spec to {}", to.extension()))?;
+    let round_trip: Value = to.parse(&out)?;
+    ensure!(
+        round_trip == doc,
+        "converting to {} would change the spec; aborting",
+        to.extension()
+    );
+
+    let output = match output {
+        Some(p) if p == Path::new("-") => {
+            print!("{out}");
+            None
+        }
+        Some(p) => Some(p.to_path_buf()),
+        None => Some(path.with_extension(to.extension())),
+    };
+    if let Some(dest) = &output {
+        if dest == path {
+            bail!("spec is already {}: {}", to.extension(), path.display());
+        }
+        if dest.exists() && !force {
+            bail!("refusing to overwrite {} (pass --force)", dest.display());
+        }
+        fs::write(dest, &out).with_context(|| format!("failed to write {}", dest.display()))?;
+    }
+
+    Ok(ConvertReport {
+        spec: location.clone(),
+        to,
+        output,
+    })
+}
+
+fn backup_path(path: &Path, now: chrono::DateTime<chrono::Local>) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "spec".to_string());
+    path.with_file_name(format!("{name}.{}.bak", now.format("%Y%m%d-%H%M%S")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backup_path, convert_file, lint, migrate_file};
+    use crate::spec_loader::{SpecFormat, SpecLocation, SpecSource, parse_spec};
+    use chrono::TimeZone;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn location(path: &Path) -> SpecLocation {
+        SpecLocation {
+            path: path.to_path_buf(),
+            source: SpecSource::Flag,
+        }
+    }
+
+    #[test]
+    fn lint_reads_spec_from_location() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("spec.json");
+        fs::write(&path, r#"{ "version": 1, "areas": [], "extra": 1 }"#).expect("write");
+
+        let report = lint(&location(&path)).expect("lint");
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "unknown-key");
+    }
+
+    #[test]
+    fn migrate_leaves_current_spec_untouched() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("spec.json");
+        let raw = r#"{ "version": 1, "areas": [] }"#;
+        fs::write(&path, raw).expect("write");
+
+        let report = migrate_file(&location(&path), true).expect("migrate");
+        assert!(!report.written);
+        assert!(report.backup.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), raw);
+    }
+
+    #[test]
+    fn backup_path_appends_timestamp() {
+        let now = chrono::Local
+            .with_ymd_and_hms(2026, 10, 16, 9, 5, 0)
+            .single()
+            .expect("time");
+        let out = backup_path(Path::new("/cfg/spec.json"), now);
+        assert_eq!(out, Path::new("/cfg/spec.json.20261016-090500.bak"));
+    }
+
+    #[test]
+    fn convert_round_trips_through_every_format() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("spec.json");
+        let raw = r#"{
+  "version": 1,
+  "areas": [
+    {
+      "name": "System",
+      "root": "~/System",
+      "required": [
+        { "path": "apps" },
+        { "path": "life-os", "children": [{ "path": "repo" }] }
+      ]
+    }
+  ]
+}"#;
+        fs::write(&path, raw).expect("write");
+
+        let report = convert_file(&location(&path), SpecFormat::Toml, None, false).expect("toml");
+        let toml_path = report.output.expect("toml output");
+        assert_eq!(toml_path, dir.path().join("spec.toml"));
+
+        let report =
+            convert_file(&location(&toml_path), SpecFormat::Yaml, None, false).expect("yaml");
+        let yaml_path = report.output.expect("yaml output");
+
+        let original = parse_spec(raw, SpecFormat::Json).expect("json");
+        let yaml = fs::read_to_string(&yaml_path).expect("read yaml");
+        let converted = parse_spec(&yaml, SpecFormat::Yaml).expect("parse yaml");
+        assert_eq!(format!("{original:?}"), format!("{converted:?}"));
+
+        let err = convert_file(&location(&path), SpecFormat::Toml, None, false)
+            .expect_err("existing output");
+        assert!(err.to_string().contains("--force"));
+    }
+}
@@ -1,119 +1,836 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::Serialize;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
+use crate::commands::dupes::same_contents;
+use crate::commands::quarantine::{self, ManifestEntry};
+use crate::disk_usage::{self, Tally};
+use crate::duration::{format_duration, parse_duration};
+use crate::journal::{self, Journal, Operation};
+use crate::rules::{self, Rule, RuleAction, parse_size};
+use crate::screenshots;
+use crate::spec::{DesktopSettings, InboxSettings, SpecFile};
+use crate::spec_loader::expand_root;
+use crate::transfer;
+use crate::trash::{self, TrashedItem};
+use crate::user_dirs::UserDirs;
+
+pub const DESKTOP: &str = "Desktop";
+pub const DOWNLOADS: &str = "Downloads";
+/// Below the Documents user dir.
+const SCREENSHOTS: &str = "screenshots";
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+const GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+/// Files browsers and download tools write to before renaming them.
+const PARTIAL_EXTENSIONS: &[&str] = &[
+    "crdownload",
+    "part",
+    "partial",
+    "download",
+    "opdownload",
+    "filepart",
+    "aria2",
+    "!ut",
+    "tmp",
+];
+const GB: u64 = 1024 * 1024 * 1024;
+
 #[derive(Debug, Clone)]
 pub struct TidyOptions {
     pub apply: bool,
-    pub delete_all_downloads: bool,
-    pub desktop: PathBuf,
-    pub downloads: PathBuf,
-    pub screenshots_dest: PathBuf,
+    /// `--all`: unmatched items leave every inbox that has a retention,
+    /// whatever their age.
+    pub ignore_age: bool,
+    pub delete_mode: DeleteMode,
+    /// Used by `DeleteMode::Quarantine`.
+    pub quarantine: PathBuf,
+    /// Home trash for `DeleteMode::Trash` (other mounts use their own).
+    pub trash: PathBuf,
+    /// Every applied operation is appended here for `life-os undo`.
+    pub journal: PathBuf,
+    /// Items modified more recently than this are left alone.
+    pub grace_period: Duration,
+    /// Sizes do not include folders on other filesystems.
+    pub one_filesystem: bool,
+    /// Tidied in order; Desktop and Downloads come first.
+    pub inboxes: Vec<Inbox>,
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct TidyReport {
-    pub desktop_screenshots: Vec<PathBuf>,
-    pub desktop_other: Vec<PathBuf>,
-    pub downloads_items: Vec<PathBuf>,
-    pub downloads_total_bytes: u64,
-    pub downloads_old_items: Vec<PathBuf>,
-    pub downloads_old_bytes: u64,
-    pub planned_downloads_deletions: Vec<PathBuf>,
-    pub planned_moves: Vec<(PathBuf, PathBuf)>,
+/// A folder where things pile up. Each item goes where the first matching
+/// rule says; unmatched items leave once they are older than `older_than`.
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    pub name: String,
+    pub path: PathBuf,
+    /// A missing folder is an error rather than reported as absent.
+    pub required: bool,
+    pub rules: Vec<Rule>,
+    /// `None` keeps unmatched items.
+    pub older_than: Option<Duration>,
+    /// Where leaving items go; `None` deletes them (see `DeleteMode`).
+    pub destination: Option<PathBuf>,
+    pub gauge: Gauge,
 }
 
-pub fn run(options: &TidyOptions) -> Result<TidyReport> {
-    let mut report = TidyReport::default();
+/// How an inbox decides it is busy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gauge {
+    /// Busy above this many routed items or this many others (the Desktop).
+    Counts { routed: usize, other: usize },
+    /// Light, moderate or heavy by total size; more than `many_items` items
+    /// bumps the level by one.
+    Size {
+        moderate_above: u64,
+        heavy_above: u64,
+        many_items: usize,
+    },
+}
 
-    let desktop_entries = read_dir_paths(&options.desktop)?;
-    let downloads_entries = read_dir_paths(&options.downloads)?;
+impl Gauge {
+    const SIZE: Gauge = Gauge::Size {
+        moderate_above: GB,
+        heavy_above: 5 * GB,
+        many_items: 100,
+    };
 
-    for path in desktop_entries {
-        if path.is_dir() {
-            report.desktop_other.push(path);
-            continue;
+    fn with_settings(
+        self,
+        moderate_above: Option<&str>,
+        heavy_above: Option<&str>,
+        many_items: Option<usize>,
+    ) -> Result<Self> {
+        let Gauge::Size {
+            moderate_above: mut moderate,
+            heavy_above: mut heavy,
+            many_items: mut many,
+        } = self
+        else {
+            return Ok(self);
+        };
+        if let Some(size) = moderate_above {
+            moderate = parse_size(size).context("in `moderate_above`")?;
         }
+        if let Some(size) = heavy_above {
+            heavy = parse_size(size).context("in `heavy_above`")?;
+        }
+        many = many_items.unwrap_or(many);
+        Ok(Gauge::Size {
+            moderate_above: moderate,
+            heavy_above: heavy,
+            many_items: many,
+        })
+    }
+}
+
+impl TidyOptions {
+    /// Desktop and Downloads where the XDG user dirs put them.
+    pub fn new(dirs: &UserDirs) -> Self {
+        let home = dirs.home();
+        Self {
+            apply: false,
+            ignore_age: false,
+            delete_mode: DeleteMode::default(),
+            quarantine: quarantine::default_root(home),
+            trash: trash::home_trash(home),
+            journal: journal::default_path(home),
+            grace_period: GRACE_PERIOD,
+            one_filesystem: false,
+            inboxes: vec![
+                Inbox {
+                    name: DESKTOP.to_string(),
+                    path: dirs.desktop(),
+                    required: false,
+                    rules: screenshots::rules(&dirs.documents().join(SCREENSHOTS), &[], &[])
+                        .expect("built-in screenshot patterns are valid"),
+                    older_than: None,
+                    destination: None,
+                    gauge: Gauge::Counts {
+                        routed: 10,
+                        other: 2,
+                    },
+                },
+                Inbox {
+                    name: DOWNLOADS.to_string(),
+                    path: dirs.downloads(),
+                    required: false,
+                    rules: Vec::new(),
+                    older_than: Some(7 * DAY),
+                    destination: None,
+                    gauge: Gauge::SIZE,
+                },
+            ],
+        }
+    }
 
-        let file_name = match path.file_name().and_then(|s| s.to_str()) {
-            Some(name) => name,
-            None => {
-                report.desktop_other.push(path);
-                continue;
+    /// Applies the spec's `rules` and `tidy` section on top of the current
+    /// values and appends the spec's own inboxes.
+    pub fn configure(&mut self, spec: &SpecFile, dirs: &UserDirs) -> Result<()> {
+        let home = dirs.home();
+        let (desktop, downloads) = (&spec.tidy.desktop, &spec.tidy.downloads);
+        if let Some(grace) = &spec.tidy.grace_period {
+            self.grace_period = parse_duration(grace)
+                .map_err(|err| anyhow::anyhow!(err))
+                .context("in `tidy.grace_period`")?;
+        }
+        self.one_filesystem = spec.tidy.one_filesystem.unwrap_or(self.one_filesystem);
+        if let Some(inbox) = self.inbox_mut(DESKTOP) {
+            if let Some(path) = &desktop.path {
+                inbox.path = expand_root(path, home);
             }
-        };
+            inbox.required = desktop.required.unwrap_or(inbox.required);
+            inbox.rules = screenshots::rules(
+                &screenshot_folder(desktop, dirs),
+                &desktop.screenshot_globs,
+                &desktop.screenshot_regexes,
+            )
+            .context("in `tidy.desktop`")?;
+            if let Gauge::Counts { routed, other } = &mut inbox.gauge {
+                *routed = desktop.busy_screenshots.unwrap_or(*routed);
+                *other = desktop.busy_other.unwrap_or(*other);
+            }
+        }
+        if let Some(inbox) = self.inbox_mut(DOWNLOADS) {
+            if let Some(path) = &downloads.path {
+                inbox.path = expand_root(path, home);
+            }
+            inbox.required = downloads.required.unwrap_or(inbox.required);
+            inbox.rules = rules::compile(&spec.rules, spec, home)?;
+            if let Some(age) = &downloads.older_than {
+                inbox.older_than = Some(age_setting(age).context("in `tidy.downloads`")?);
+            }
+            inbox.gauge = inbox
+                .gauge
+                .with_settings(
+                    downloads.moderate_above.as_deref(),
+                    downloads.heavy_above.as_deref(),
+                    downloads.many_items,
+                )
+                .context("in `tidy.downloads`")?;
+        }
 
-        if is_macos_screenshot(file_name) {
-            report.desktop_screenshots.push(path.clone());
-            let dest = unique_destination(&options.screenshots_dest, file_name);
-            report.planned_moves.push((path, dest));
-        } else {
-            report.desktop_other.push(path);
+        for settings in &spec.tidy.inboxes {
+            let inbox = custom_inbox(settings, spec, home)
+                .with_context(|| format!("in inbox `{}`", settings.name))?;
+            if self.inbox_mut(&inbox.name).is_some() {
+                bail!("inbox `{}` is defined twice", inbox.name);
+            }
+            self.inboxes.push(inbox);
         }
+        Ok(())
     }
 
-    let cutoff = SystemTime::now()
-        .checked_sub(Duration::from_secs(7 * 24 * 60 * 60))
-        .context("failed to compute cutoff time")?;
+    /// `--older-than`: replaces the retention of every inbox that has one.
+    pub fn override_older_than(&mut self, age: Duration) {
+        for inbox in &mut self.inboxes {
+            if inbox.older_than.is_some() {
+                inbox.older_than = Some(age);
+            }
+        }
+    }
 
-    for path in downloads_entries {
-        let file_name = match path.file_name().and_then(|s| s.to_str()) {
-            Some(name) => name,
-            None => continue,
-        };
-        if file_name.starts_with('.') {
-            continue;
+    /// `--inbox`: drops every other inbox.
+    pub fn select_inbox(&mut self, name: &str) -> Result<()> {
+        if self.inbox_mut(name).is_none() {
+            let known: Vec<_> = self.inboxes.iter().map(|i| i.name.as_str()).collect();
+            bail!("unknown inbox `{name}` (known: {})", known.join(", "));
         }
+        self.inboxes
+            .retain(|inbox| inbox.name.eq_ignore_ascii_case(name));
+        Ok(())
+    }
+
+    fn inbox_mut(&mut self, name: &str) -> Option<&mut Inbox> {
+        self.inboxes
+            .iter_mut()
+            .find(|inbox| inbox.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Where the Desktop's screenshots are archived.
+pub fn screenshot_folder(desktop: &DesktopSettings, dirs: &UserDirs) -> PathBuf {
+    match &desktop.screenshots {
+        Some(dest) => expand_root(dest, dirs.home()),
+        None => dirs.documents().join(SCREENSHOTS),
+    }
+}
+
+fn age_setting(age: &str) -> Result<Duration> {
+    parse_duration(age)
+        .map_err(|err| anyhow::anyhow!(err))
+        .context("in `older_than`")
+}
+
+fn custom_inbox(settings: &InboxSettings, spec: &SpecFile, home: &Path) -> Result<Inbox> {
+    let path = settings.path.as_deref().context("no `path` set")?;
+    Ok(Inbox {
+        name: settings.name.clone(),
+        path: expand_root(path, home),
+        required: settings.required.unwrap_or(false),
+        rules: rules::compile(&settings.rules, spec, home)?,
+        older_than: settings
+            .older_than
+            .as_deref()
+            .map(age_setting)
+            .transpose()?,
+        destination: settings
+            .destination
+            .as_deref()
+            .map(|target| rules::resolve_target(target, spec, home))
+            .transpose()
+            .context("in `destination`")?,
+        gauge: Gauge::SIZE.with_settings(
+            settings.moderate_above.as_deref(),
+            settings.heavy_above.as_deref(),
+            settings.many_items,
+        )?,
+    })
+}
+
+#[derive(Debug, Default, Clone, Serialize, JsonSchema)]
+pub struct TidyReport {
+    /// False for a dry run.
+    pub applied: bool,
+    /// Journal run ID of an applied run (`life-os undo <run_id>`).
+    pub run_id: Option<String>,
+    pub delete_mode: DeleteMode,
+    /// Items modified within this (like `5m`) are skipped.
+    pub grace_period: String,
+    pub inboxes: Vec<InboxReport>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, JsonSchema)]
+pub struct InboxReport {
+    pub name: String,
+    pub path: PathBuf,
+    /// The folder does not exist; nothing else is filled in then.
+    pub absent: bool,
+    /// Why the inbox could not be scanned (a required folder is missing,
+    /// permission denied, ...); nothing else is filled in then.
+    pub error: Option<String>,
+    /// Everything in the inbox except hidden entries.
+    pub items: Vec<PathBuf>,
+    /// Apparent size; hard-linked files count once.
+    pub total_bytes: u64,
+    /// Disk space in use (blocks).
+    pub allocated_bytes: u64,
+    /// Retention like `7d`; null when unmatched items may stay.
+    pub older_than: Option<String>,
+    pub old_items: Vec<PathBuf>,
+    pub old_bytes: u64,
+    pub busy: bool,
+    /// Null for inboxes gauged by item counts (the Desktop).
+    pub level: Option<InboxLevel>,
+    /// Every item a rule matched, plus those past the retention (or all
+    /// unmatched ones with `--all`).
+    pub planned: Vec<PlannedItem>,
+    /// Items left alone because they may still be written to.
+    pub skipped: Vec<SkippedItem>,
+    /// Items moved by this run.
+    pub moved: Vec<PlannedMove>,
+    /// Items moved to quarantine by this run.
+    pub quarantined: Vec<ManifestEntry>,
+    /// Items moved to the freedesktop Trash by this run.
+    pub trashed: Vec<TrashedItem>,
+    /// Items removed for good by this run.
+    pub deleted: Vec<PathBuf>,
+}
 
-        let size = dir_or_file_size(&path);
-        report.downloads_total_bytes = report.downloads_total_bytes.saturating_add(size);
-        report.downloads_items.push(path.clone());
+/// What "delete" means for inbox items.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// freedesktop.org Trash (restorable from file managers)
+    Trash,
+    /// Dated bucket in System/life-os/quarantine
+    #[default]
+    Quarantine,
+    /// Remove immediately (cannot be undone)
+    Permanent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct PlannedMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct PlannedItem {
+    pub path: PathBuf,
+    /// Label of the matching rule; null when the retention applies.
+    pub rule: Option<String>,
+    pub action: RuleAction,
+    /// Where a move puts the item.
+    pub destination: Option<PathBuf>,
+    /// What happened to the item; null in a dry run.
+    pub outcome: Option<Outcome>,
+    /// The identical file already at the destination; the item is
+    /// quarantined instead of moved.
+    pub duplicate_of: Option<PathBuf>,
+    /// Size and mtime when scanned, compared again before acting.
+    #[serde(skip)]
+    seen: (u64, Option<SystemTime>),
+}
 
-        if is_older_than(&path, cutoff) {
-            report.downloads_old_items.push(path.clone());
-            report.downloads_old_bytes = report.downloads_old_bytes.saturating_add(size);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Done {
+        /// The action happened but could not be journaled, so `undo`
+        /// cannot reverse it.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        journal_error: Option<String>,
+        /// The item was quarantined but is missing from the bucket's
+        /// manifest.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        manifest_error: Option<String>,
+    },
+    /// Nothing was done, e.g. because the item disappeared.
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct SkippedItem {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// A partial download (`.crdownload`, `.part`, ...).
+    InProgress,
+    /// Its size or modification time changed between the scan and
+    /// `--apply` acting on it.
+    Changed,
+    /// Modified within the grace period.
+    TooNew,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum InboxLevel {
+    #[default]
+    Light,
+    Moderate,
+    Heavy,
+}
+
+impl InboxLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InboxLevel::Light => "light",
+            InboxLevel::Moderate => "moderate",
+            InboxLevel::Heavy => "heavy",
         }
+    }
 
-        if options.delete_all_downloads || is_older_than(&path, cutoff) {
-            report.planned_downloads_deletions.push(path);
+    fn bump(self) -> Self {
+        match self {
+            InboxLevel::Light => InboxLevel::Moderate,
+            InboxLevel::Moderate => InboxLevel::Heavy,
+            InboxLevel::Heavy => InboxLevel::Heavy,
         }
     }
 
+    pub fn is_light(self) -> bool {
+        matches!(self, InboxLevel::Light)
+    }
+}
+
+impl TidyReport {
+    /// Inboxes that could not be scanned and applied items whose action
+    /// failed, with the error text.
+    pub fn failures(&self) -> impl Iterator<Item = (&PathBuf, &str)> {
+        let inboxes = self
+            .inboxes
+            .iter()
+            .filter_map(|inbox| Some((&inbox.path, inbox.error.as_deref()?)));
+        let items = self
+            .inboxes
+            .iter()
+            .flat_map(|inbox| &inbox.planned)
+            .filter_map(|planned| match &planned.outcome {
+                Some(Outcome::Failed { error }) => Some((&planned.path, error.as_str())),
+                _ => None,
+            });
+        inboxes.chain(items)
+    }
+
+    /// Applied items missing from the journal, with the journal error.
+    pub fn unjournaled(&self) -> impl Iterator<Item = (&PathBuf, &str)> {
+        self.inboxes
+            .iter()
+            .flat_map(|inbox| &inbox.planned)
+            .filter_map(|planned| match &planned.outcome {
+                Some(Outcome::Done {
+                    journal_error: Some(error),
+                    ..
+                }) => Some((&planned.path, error.as_str())),
+                _ => None,
+            })
+    }
+
+    /// Quarantined items missing from the manifest, with the write error.
+    pub fn unlisted(&self) -> impl Iterator<Item = (&PathBuf, &str)> {
+        self.inboxes
+            .iter()
+            .flat_map(|inbox| &inbox.planned)
+            .filter_map(|planned| match &planned.outcome {
+                Some(Outcome::Done {
+                    manifest_error: Some(error),
+                    ..
+                }) => Some((&planned.path, error.as_str())),
+                _ => None,
+            })
+    }
+}
+
+impl InboxReport {
+    /// `absent`, `clean`/`busy` for count-gauged inboxes, else the level.
+    pub fn status(&self) -> &'static str {
+        if self.error.is_some() {
+            return "failed";
+        }
+        if self.absent {
+            return "absent";
+        }
+        match (self.level, self.busy) {
+            (Some(level), _) => level.as_str(),
+            (None, true) => "busy",
+            (None, false) => "clean",
+        }
+    }
+}
+
+fn size_level(total_bytes: u64, items: usize, gauge: Gauge) -> Option<InboxLevel> {
+    let Gauge::Size {
+        moderate_above,
+        heavy_above,
+        many_items,
+    } = gauge
+    else {
+        return None;
+    };
+    let level = if total_bytes <= moderate_above {
+        InboxLevel::Light
+    } else if total_bytes <= heavy_above {
+        InboxLevel::Moderate
+    } else {
+        InboxLevel::Heavy
+    };
+    Some(if items > many_items {
+        level.bump()
+    } else {
+        level
+    })
+}
+
+pub fn run(options: &TidyOptions) -> Result<TidyReport> {
+    let mut report = TidyReport {
+        applied: options.apply,
+        delete_mode: options.delete_mode,
+        grace_period: format_duration(options.grace_period),
+        ..TidyReport::default()
+    };
+
+    let started = SystemTime::now();
+    for inbox in &options.inboxes {
+        // One unreadable inbox does not stop the others.
+        let scanned = scan(inbox, options, started).unwrap_or_else(|err| InboxReport {
+            name: inbox.name.clone(),
+            path: inbox.path.clone(),
+            error: Some(format!("{err:#}")),
+            ..InboxReport::default()
+        });
+        report.inboxes.push(scanned);
+    }
+
     if options.apply {
-        if !report.planned_moves.is_empty() {
-            fs::create_dir_all(&options.screenshots_dest).with_context(|| {
-                format!(
-                    "failed to create screenshots destination: {}",
-                    options.screenshots_dest.display()
-                )
-            })?;
+        let now = Local::now();
+        let journal = Journal::start(&options.journal, now);
+        report.run_id = Some(journal.run_id().to_string());
+        for inbox in &mut report.inboxes {
+            apply_plan(options, &journal, now, inbox);
         }
+    }
 
-        for (src, dest) in &report.planned_moves {
-            fs::rename(src, dest).with_context(|| {
-                format!(
-                    "failed to move screenshot {} -> {}",
-                    src.display(),
-                    dest.display()
-                )
-            })?;
+    Ok(report)
+}
+
+fn scan(inbox: &Inbox, options: &TidyOptions, now: SystemTime) -> Result<InboxReport> {
+    let mut report = InboxReport {
+        name: inbox.name.clone(),
+        path: inbox.path.clone(),
+        older_than: inbox.older_than.map(format_duration),
+        ..InboxReport::default()
+    };
+    let cutoff = inbox.older_than.and_then(|age| now.checked_sub(age));
+    let mut tally = tally(options, &inbox.path);
+
+    if fs::symlink_metadata(&inbox.path).is_err_and(|err| err.kind() == ErrorKind::NotFound) {
+        if inbox.required {
+            bail!(
+                "inbox `{}` is required but {} does not exist",
+                inbox.name,
+                inbox.path.display()
+            );
         }
+        report.absent = true;
+        return Ok(report);
+    }
 
-        for path in &report.planned_downloads_deletions {
-            if path.is_dir() {
-                fs::remove_dir_all(path)
-                    .with_context(|| format!("failed to delete dir: {}", path.display()))?;
+    for path in read_dir_paths(&inbox.path)? {
+        let Some(file_name) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        let (usage, counted) = tally.measure(&path);
+        let size = usage.apparent;
+        report.total_bytes = report.total_bytes.saturating_add(counted.apparent);
+        report.allocated_bytes = report.allocated_bytes.saturating_add(counted.allocated);
+        report.items.push(path.clone());
+
+        let old = cutoff.is_some_and(|cutoff| is_older_than(&path, cutoff));
+        if old {
+            report.old_items.push(path.clone());
+            report.old_bytes = report.old_bytes.saturating_add(counted.apparent);
+        }
+
+        let item = rules::Item {
+            name: file_name,
+            size,
+            age: modified_age(&path, now),
+        };
+        let expired = old || (options.ignore_age && cutoff.is_some());
+        let (rule, action, dir) = match inbox.rules.iter().find(|rule| rule.matches(&item)) {
+            Some(rule) => {
+                let dir = rule
+                    .destination
+                    .as_ref()
+                    .map(|dir| match rule.date_buckets {
+                        true => dir.join(screenshots::bucket(&path)),
+                        false => dir.clone(),
+                    });
+                (Some(rule.label.clone()), rule.action, dir)
+            }
+            None if expired => match &inbox.destination {
+                Some(dir) => (None, RuleAction::MoveTo, Some(dir.clone())),
+                None => (None, RuleAction::Delete, None),
+            },
+            None => continue,
+        };
+        if action != RuleAction::Keep
+            && let Some(reason) = skip_reason(file_name, item.age, options.grace_period)
+        {
+            report.skipped.push(SkippedItem { path, reason });
+            continue;
+        }
+        let mut planned = PlannedItem {
+            path: path.clone(),
+            rule,
+            action,
+            destination: None,
+            outcome: None,
+            duplicate_of: None,
+            seen: (size, modified_time(&path)),
+        };
+        if let Some(dir) = dir {
+            let existing = dir.join(file_name);
+            if same_contents(&path, &existing).unwrap_or(false) {
+                planned.action = RuleAction::Quarantine;
+                planned.duplicate_of = Some(existing);
             } else {
-                fs::remove_file(path)
-                    .with_context(|| format!("failed to delete file: {}", path.display()))?;
+                planned.destination = Some(unique_destination(&dir, file_name));
             }
         }
+        report.planned.push(planned);
     }
 
+    report.level = size_level(report.total_bytes, report.items.len(), inbox.gauge);
+    report.busy = match inbox.gauge {
+        Gauge::Counts { routed, other } => {
+            let moving = report
+                .planned
+                .iter()
+                .filter(|p| p.action != RuleAction::Keep)
+                .count();
+            moving > routed || report.items.len() - moving > other
+        }
+        Gauge::Size { .. } => !report.level.is_some_and(InboxLevel::is_light),
+    };
     Ok(report)
 }
 
+/// Attempts every planned item; a failure is recorded on the item and the
+/// run moves on to the next one.
+fn apply_plan(
+    options: &TidyOptions,
+    journal: &Journal,
+    now: DateTime<Local>,
+    report: &mut InboxReport,
+) {
+    for mut planned in std::mem::take(&mut report.planned) {
+        if planned.action != RuleAction::Keep && changed_since_scan(&planned, options) {
+            report.skipped.push(SkippedItem {
+                path: planned.path,
+                reason: SkipReason::Changed,
+            });
+            continue;
+        }
+        let outcome = match apply_item(options, journal, now, report, &mut planned) {
+            Ok(outcome) => outcome,
+            Err(err) => Outcome::Failed {
+                error: format!("{err:#}"),
+            },
+        };
+        planned.outcome = Some(outcome);
+        report.planned.push(planned);
+    }
+}
+
+/// Something still writing to an item shows up as a changed size or mtime.
+/// A missing item is left to `apply_item`.
+fn changed_since_scan(planned: &PlannedItem, options: &TidyOptions) -> bool {
+    let Some(modified) = modified_time(&planned.path) else {
+        return false;
+    };
+    let root = planned.path.parent().unwrap_or(Path::new("/"));
+    let size = tally(options, root).measure(&planned.path).0;
+    (size.apparent, Some(modified)) != planned.seen
+}
+
+/// Sizes items in `root`, on its filesystem only with `one_filesystem`.
+fn tally(options: &TidyOptions, root: &Path) -> Tally {
+    if options.one_filesystem {
+        Tally::within(root)
+    } else {
+        Tally::default()
+    }
+}
+
+fn apply_item(
+    options: &TidyOptions,
+    journal: &Journal,
+    now: DateTime<Local>,
+    report: &mut InboxReport,
+    planned: &mut PlannedItem,
+) -> Result<Outcome> {
+    let path = &planned.path;
+    if planned.action == RuleAction::Keep {
+        return Ok(journaled(Ok(())));
+    }
+    if fs::symlink_metadata(path).is_err() {
+        return Ok(Outcome::Skipped {
+            reason: "no longer exists".to_string(),
+        });
+    }
+    let recorded = match planned.action {
+        RuleAction::Keep => Ok(()),
+        RuleAction::MoveTo => {
+            let Some(mut dest) = planned.destination.clone() else {
+                bail!("no destination for {}", path.display());
+            };
+            // An earlier item of this run may have put the same file there.
+            if same_contents(path, &dest).unwrap_or(false) {
+                let outcome = quarantine_item(options, journal, path, now, report)?;
+                planned.action = RuleAction::Quarantine;
+                planned.destination = None;
+                planned.duplicate_of = Some(dest);
+                return Ok(outcome);
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create directory: {}", parent.display()))?;
+                // An earlier item of this run may have taken the name.
+                if fs::symlink_metadata(&dest).is_ok()
+                    && let Some(name) = dest.file_name().and_then(|n| n.to_str())
+                {
+                    dest = unique_destination(parent, name);
+                }
+            }
+            let size = disk_usage::size(path);
+            transfer::move_path(path, &dest)?;
+            report.moved.push(PlannedMove {
+                from: path.clone(),
+                to: dest.clone(),
+            });
+            planned.destination = Some(dest.clone());
+            journal.record(Operation::Move, path, Some(&dest), size, None)
+        }
+        RuleAction::Quarantine => return quarantine_item(options, journal, path, now, report),
+        RuleAction::Delete => match options.delete_mode {
+            DeleteMode::Quarantine => {
+                return quarantine_item(options, journal, path, now, report);
+            }
+            DeleteMode::Trash => {
+                let item = trash::trash(path, &options.trash, now)?;
+                let recorded = journal.record(
+                    Operation::Trash,
+                    &item.original,
+                    Some(&item.stored),
+                    item.size,
+                    None,
+                );
+                report.trashed.push(item);
+                recorded
+            }
+            DeleteMode::Permanent => {
+                let size = disk_usage::size(path);
+                trash::delete_permanently(path)?;
+                report.deleted.push(path.clone());
+                journal.record(Operation::Delete, path, None, size, None)
+            }
+        },
+    };
+    Ok(journaled(recorded))
+}
+
+/// `Done`, noting a journal write that failed after the action succeeded.
+fn journaled(recorded: Result<()>) -> Outcome {
+    Outcome::Done {
+        journal_error: recorded.err().map(|err| format!("{err:#}")),
+        manifest_error: None,
+    }
+}
+
+fn quarantine_item(
+    options: &TidyOptions,
+    journal: &Journal,
+    path: &Path,
+    now: DateTime<Local>,
+    report: &mut InboxReport,
+) -> Result<Outcome> {
+    let (entry, listed) = quarantine::store(&options.quarantine, path, now)?;
+    // Reported even if journaling fails: the item has moved either way.
+    let recorded = journal.record(
+        Operation::Quarantine,
+        &entry.original,
+        Some(&entry.stored),
+        entry.size,
+        None,
+    );
+    report.quarantined.push(entry);
+    Ok(Outcome::Done {
+        journal_error: recorded.err().map(|err| format!("{err:#}")),
+        manifest_error: listed.err().map(|err| format!("{err:#}")),
+    })
+}
+
 fn read_dir_paths(dir: &Path) -> Result<Vec<PathBuf>> {
     let mut out = Vec::new();
     for entry in
@@ -125,11 +842,7 @@ fn read_dir_paths(dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(out)
 }
 
-fn is_macos_screenshot(file_name: &str) -> bool {
-    file_name.starts_with("Screenshot ") && file_name.ends_with(".png")
-}
-
-fn unique_destination(dest_dir: &Path, file_name: &str) -> PathBuf {
+pub fn unique_destination(dest_dir: &Path, file_name: &str) -> PathBuf {
     let base_dest = dest_dir.join(file_name);
     if !base_dest.exists() {
         return base_dest;
@@ -154,38 +867,34 @@ fn split_name_ext(file_name: &str) -> (String, String) {
     }
 }
 
-pub fn dir_or_file_size(path: &Path) -> u64 {
-    match fs::metadata(path) {
-        Ok(meta) if meta.is_file() => meta.len(),
-        Ok(meta) if meta.is_dir() => dir_size(path),
-        _ => 0,
+fn skip_reason(name: &str, age: Duration, grace_period: Duration) -> Option<SkipReason> {
+    let partial = name
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| PARTIAL_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+    if partial {
+        Some(SkipReason::InProgress)
+    } else if age < grace_period {
+        Some(SkipReason::TooNew)
+    } else {
+        None
     }
 }
 
-fn dir_size(path: &Path) -> u64 {
-    let mut total = 0u64;
-    let entries = match fs::read_dir(path) {
-        Ok(entries) => entries,
-        Err(_) => return 0,
-    };
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::symlink_metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
 
-    for entry in entries.flatten() {
-        let p = entry.path();
-        total = total.saturating_add(dir_or_file_size(&p));
-    }
-    total
+/// Time since `path` was last modified; zero if unknown or in the future.
+fn modified_age(path: &Path, now: SystemTime) -> Duration {
+    modified_time(path)
+        .and_then(|modified| now.duration_since(modified).ok())
+        .unwrap_or_default()
 }
 
 fn is_older_than(path: &Path, cutoff: SystemTime) -> bool {
-    let meta = match fs::metadata(path) {
-        Ok(meta) => meta,
-        Err(_) => return false,
-    };
-    let modified = match meta.modified() {
-        Ok(m) => m,
-        Err(_) => return false,
-    };
-    modified < cutoff
+    modified_time(path).is_some_and(|modified| modified < cutoff)
 }
 
 pub fn human_bytes(bytes: u64) -> String {
@@ -204,7 +913,15 @@ pub fn human_bytes(bytes: u64) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{TidyOptions, run};
+    use super::{
+        DESKTOP, DOWNLOADS, DeleteMode, InboxLevel, InboxReport, Outcome, SkipReason, TidyOptions,
+        TidyReport, apply_plan, run, scan,
+    };
+    use crate::journal;
+    use crate::rules::RuleAction;
+    use crate::spec::SpecFile;
+    use crate::user_dirs::UserDirs;
+    use chrono::Local;
     use filetime::{FileTime, set_file_times};
     use std::fs;
     use std::path::Path;
@@ -216,61 +933,76 @@ mod tests {
         fs::write(path, data).expect("write file");
     }
 
+    fn set_age(path: &Path, days: u64) {
+        let time = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+        let time = FileTime::from_system_time(time);
+        set_file_times(path, time, time).expect("set mtime");
+    }
+
+    /// Desktop and Downloads under `home`, both created.
+    fn options(home: &Path) -> TidyOptions {
+        fs::create_dir_all(home.join("Desktop")).expect("desktop");
+        fs::create_dir_all(home.join("Downloads")).expect("downloads");
+        TidyOptions {
+            trash: home.join(".local/share/Trash"),
+            grace_period: Duration::ZERO,
+            ..TidyOptions::new(&UserDirs::defaults(home))
+        }
+    }
+
+    fn inbox<'a>(report: &'a TidyReport, name: &str) -> &'a InboxReport {
+        report
+            .inboxes
+            .iter()
+            .find(|inbox| inbox.name == name)
+            .expect("inbox in report")
+    }
+
+    fn spec(raw: &str) -> SpecFile {
+        serde_json::from_str(raw).expect("spec")
+    }
+
     #[test]
     fn dry_run_reports_desktop_screenshots_and_other_files() {
         let dir = tempdir().expect("tempdir");
+        let options = options(dir.path());
         let desktop = dir.path().join("Desktop");
-        let downloads = dir.path().join("Downloads");
-        let screenshots_dest = dir.path().join("Documents/screenshots");
-
-        fs::create_dir_all(&desktop).expect("desktop");
-        fs::create_dir_all(&downloads).expect("downloads");
-
         write_file(&desktop.join("Screenshot 2026-02-09 at 10.00.00.png"), 10);
         write_file(&desktop.join("notes.txt"), 5);
-
-        let options = TidyOptions {
-            apply: false,
-            delete_all_downloads: false,
-            desktop: desktop.clone(),
-            downloads: downloads.clone(),
-            screenshots_dest,
-        };
+        write_file(&desktop.join(".DS_Store"), 5);
 
         let report = run(&options).expect("tidy run");
-        assert_eq!(report.desktop_screenshots.len(), 1);
-        assert_eq!(report.desktop_other.len(), 1);
+        let desktop = inbox(&report, DESKTOP);
+        assert_eq!(desktop.items.len(), 2);
+        assert_eq!(desktop.planned.len(), 1);
+        assert_eq!(desktop.planned[0].rule.as_deref(), Some("`screenshots`"));
+        assert_eq!(desktop.status(), "clean");
     }
 
     #[test]
     fn apply_moves_screenshots_and_renames_on_collision() {
         let dir = tempdir().expect("tempdir");
-        let desktop = dir.path().join("Desktop");
-        let downloads = dir.path().join("Downloads");
-        let screenshots_dest = dir.path().join("Documents/screenshots");
-
-        fs::create_dir_all(&desktop).expect("desktop");
-        fs::create_dir_all(&downloads).expect("downloads");
+        let mut options = options(dir.path());
+        options.apply = true;
+        let screenshots_dest = dir.path().join("Documents/screenshots/2026/02");
         fs::create_dir_all(&screenshots_dest).expect("dest");
 
-        let screenshot = desktop.join("Screenshot 2026-02-09 at 10.00.00.png");
+        let screenshot = dir
+            .path()
+            .join("Desktop/Screenshot 2026-02-09 at 10.00.00.png");
         write_file(&screenshot, 10);
         write_file(
             &screenshots_dest.join("Screenshot 2026-02-09 at 10.00.00.png"),
             1,
         );
 
-        let options = TidyOptions {
-            apply: true,
-            delete_all_downloads: false,
-            desktop: desktop.clone(),
-            downloads: downloads.clone(),
-            screenshots_dest: screenshots_dest.clone(),
-        };
-
-        let _report = run(&options).expect("tidy run");
+        let report = run(&options).expect("tidy run");
 
         assert!(!screenshot.exists());
+        let entries = journal::read(&options.journal).expect("journal");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(Some(&entries[0].run_id), report.run_id.as_ref());
+        assert_eq!(entries[0].source, screenshot);
         assert!(
             screenshots_dest
                 .join("Screenshot 2026-02-09 at 10.00.00 (1).png")
@@ -281,38 +1013,24 @@ mod tests {
     #[test]
     fn downloads_reports_total_size_and_excludes_hidden_items() {
         let dir = tempdir().expect("tempdir");
-        let desktop = dir.path().join("Desktop");
+        let options = options(dir.path());
         let downloads = dir.path().join("Downloads");
-        let screenshots_dest = dir.path().join("Documents/screenshots");
-
-        fs::create_dir_all(&desktop).expect("desktop");
-        fs::create_dir_all(&downloads).expect("downloads");
-
         write_file(&downloads.join("a.txt"), 5);
         write_file(&downloads.join(".hidden"), 10);
 
-        let options = TidyOptions {
-            apply: false,
-            delete_all_downloads: false,
-            desktop,
-            downloads: downloads.clone(),
-            screenshots_dest,
-        };
-
         let report = run(&options).expect("tidy run");
-        assert_eq!(report.downloads_total_bytes, 5);
-        assert_eq!(report.downloads_items.len(), 1);
+        let downloads = inbox(&report, DOWNLOADS);
+        assert_eq!(downloads.total_bytes, 5);
+        assert_eq!(downloads.items.len(), 1);
+        assert_eq!(downloads.level, Some(InboxLevel::Light));
     }
 
     #[test]
     fn apply_deletes_downloads_older_than_7_days_by_mtime() {
         let dir = tempdir().expect("tempdir");
-        let desktop = dir.path().join("Desktop");
+        let mut options = options(dir.path());
+        options.apply = true;
         let downloads = dir.path().join("Downloads");
-        let screenshots_dest = dir.path().join("Documents/screenshots");
-
-        fs::create_dir_all(&desktop).expect("desktop");
-        fs::create_dir_all(&downloads).expect("downloads");
 
         let old_file = downloads.join("old.txt");
         let new_file = downloads.join("new.txt");
@@ -324,31 +1042,29 @@ mod tests {
         fs::create_dir_all(&old_dir).expect("old dir");
         write_file(&hidden_old, 5);
 
-        let now = SystemTime::now();
-        let eight_days = Duration::from_secs(8 * 24 * 60 * 60);
-        let two_days = Duration::from_secs(2 * 24 * 60 * 60);
-        let old_time = FileTime::from_system_time(now - eight_days);
-        let new_time = FileTime::from_system_time(now - two_days);
-
-        set_file_times(&old_file, old_time, old_time).expect("set old time");
-        set_file_times(&old_dir, old_time, old_time).expect("set old dir time");
-        set_file_times(&hidden_old, old_time, old_time).expect("set hidden time");
-        set_file_times(&new_file, new_time, new_time).expect("set new time");
-
-        let options = TidyOptions {
-            apply: true,
-            delete_all_downloads: false,
-            desktop,
-            downloads: downloads.clone(),
-            screenshots_dest,
-        };
+        set_age(&old_file, 8);
+        set_age(&old_dir, 8);
+        set_age(&hidden_old, 8);
+        set_age(&new_file, 2);
 
-        let _report = run(&options).expect("tidy run");
+        let report = run(&options).expect("tidy run");
 
         assert!(!old_file.exists());
         assert!(!old_dir.exists());
         assert!(new_file.exists());
         assert!(hidden_old.exists());
+
+        // Nothing is deleted for real; both items sit in today's bucket.
+        let quarantined = &inbox(&report, DOWNLOADS).quarantined;
+        assert_eq!(quarantined.len(), 2);
+        for entry in quarantined {
+            assert!(entry.stored.exists());
+            assert!(
+                entry
+                    .stored
+                    .starts_with(dir.path().join("System/life-os/quarantine"))
+            );
+        }
     }
 
     #[test]
@@ -363,32 +1079,467 @@ mod tests {
     #[test]
     fn apply_deletes_all_downloads_when_flag_set() {
         let dir = tempdir().expect("tempdir");
-        let desktop = dir.path().join("Desktop");
+        let mut options = options(dir.path());
+        options.apply = true;
+        options.ignore_age = true;
         let downloads = dir.path().join("Downloads");
-        let screenshots_dest = dir.path().join("Documents/screenshots");
-
-        fs::create_dir_all(&desktop).expect("desktop");
-        fs::create_dir_all(&downloads).expect("downloads");
 
         let file = downloads.join("file.txt");
         let dir_item = downloads.join("dir");
         let hidden = downloads.join(".hidden");
+        let note = dir.path().join("Desktop/note.txt");
         write_file(&file, 5);
         fs::create_dir_all(&dir_item).expect("dir");
         write_file(&hidden, 5);
-
-        let options = TidyOptions {
-            apply: true,
-            delete_all_downloads: true,
-            desktop,
-            downloads: downloads.clone(),
-            screenshots_dest,
-        };
+        write_file(&note, 5);
 
         let _report = run(&options).expect("tidy run");
 
         assert!(!file.exists());
         assert!(!dir_item.exists());
         assert!(hidden.exists());
+        // The Desktop has no retention, so `--all` leaves it alone.
+        assert!(note.exists());
+    }
+
+    #[test]
+    fn apply_honors_trash_and_permanent_delete_modes() {
+        let dir = tempdir().expect("tempdir");
+        let mut options = options(dir.path());
+        options.apply = true;
+        options.ignore_age = true;
+        options.delete_mode = DeleteMode::Trash;
+        let downloads = dir.path().join("Downloads");
+
+        write_file(&downloads.join("invoice.pdf"), 5);
+        let report = run(&options).expect("tidy run");
+        assert_eq!(inbox(&report, DOWNLOADS).trashed.len(), 1);
+        assert!(options.trash.join("files/invoice.pdf").exists());
+        assert!(options.trash.join("info/invoice.pdf.trashinfo").exists());
+        assert!(!options.quarantine.exists());
+
+        write_file(&downloads.join("junk.bin"), 5);
+        options.delete_mode = DeleteMode::Permanent;
+        let report = run(&options).expect("tidy run");
+        assert_eq!(
+            inbox(&report, DOWNLOADS).deleted,
+            vec![downloads.join("junk.bin")]
+        );
+        assert!(!downloads.join("junk.bin").exists());
+        assert!(!options.trash.join("files/junk.bin").exists());
+    }
+
+    #[test]
+    fn spec_settings_override_paths_and_limits() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let desktop = home.join("Inbox/Desk");
+        let downloads = home.join("Inbox/Down");
+        fs::create_dir_all(&desktop).expect("desktop");
+        fs::create_dir_all(&downloads).expect("downloads");
+        write_file(&desktop.join("Screenshot 1.png"), 1);
+        write_file(&desktop.join("Screenshot 2.png"), 1);
+        write_file(&downloads.join("ten-days.bin"), 2048);
+        set_age(&downloads.join("ten-days.bin"), 10);
+
+        let spec = spec(
+            r#"{
+  "version": 1,
+  "tidy": {
+    "grace_period": "0s",
+    "desktop": { "path": "~/Inbox/Desk", "screenshots": "~/Pictures/shots", "busy_screenshots": 1 },
+    "downloads": { "path": "~/Inbox/Down", "older_than": "14d", "moderate_above": "1KB" }
+  }
+}"#,
+        );
+        let dirs = UserDirs::defaults(home);
+        let mut options = TidyOptions::new(&dirs);
+        options.configure(&spec, &dirs).expect("configure");
+        assert_eq!(options.inboxes[0].path, desktop);
+
+        let report = run(&options).expect("tidy run");
+        let desk = inbox(&report, DESKTOP);
+        assert!(desk.busy);
+        assert_eq!(desk.status(), "busy");
+        // No date in the name: bucketed by modification time.
+        let bucket = Local::now().format("%Y/%m").to_string();
+        assert_eq!(
+            desk.planned[0].destination.as_ref().unwrap().parent(),
+            Some(home.join("Pictures/shots").join(bucket).as_path())
+        );
+        let down = inbox(&report, DOWNLOADS);
+        assert_eq!(down.level, Some(InboxLevel::Moderate));
+        assert_eq!(down.older_than.as_deref(), Some("14d"));
+        assert!(down.planned.is_empty());
+
+        // `--older-than` is applied after the spec.
+        options.override_older_than(Duration::from_secs(7 * 24 * 60 * 60));
+        let report = run(&options).expect("tidy run");
+        assert_eq!(inbox(&report, DOWNLOADS).planned.len(), 1);
+    }
+
+    #[test]
+    fn partial_and_recent_downloads_are_skipped_with_a_reason() {
+        let dir = tempdir().expect("tempdir");
+        let mut options = options(dir.path());
+        options.apply = true;
+        options.ignore_age = true;
+        options.grace_period = Duration::from_secs(60 * 60);
+        let downloads = dir.path().join("Downloads");
+        for name in ["movie.mkv.part", "setup.CRDOWNLOAD", "old.txt"] {
+            write_file(&downloads.join(name), 5);
+            set_age(&downloads.join(name), 30);
+        }
+        write_file(&downloads.join("just-saved.txt"), 5);
+
+        let report = run(&options).expect("tidy run");
+        let down = inbox(&report, DOWNLOADS);
+        let mut skipped: Vec<_> = down
+            .skipped
+            .iter()
+            .map(|s| (s.path.file_name().unwrap().to_str().unwrap(), s.reason))
+            .collect();
+        skipped.sort_by_key(|(name, _)| *name);
+        assert_eq!(
+            skipped,
+            vec![
+                ("just-saved.txt", SkipReason::TooNew),
+                ("movie.mkv.part", SkipReason::InProgress),
+                ("setup.CRDOWNLOAD", SkipReason::InProgress),
+            ]
+        );
+        assert_eq!(down.quarantined.len(), 1);
+        assert!(downloads.join("movie.mkv.part").exists());
+        assert!(downloads.join("just-saved.txt").exists());
+        assert!(!downloads.join("old.txt").exists());
+    }
+
+    #[test]
+    fn items_changed_after_the_scan_are_skipped_when_applying() {
+        let dir = tempdir().expect("tempdir");
+        let mut options = options(dir.path());
+        options.apply = true;
+        let downloads = dir.path().join("Downloads");
+        for name in ["growing.zip", "done.zip"] {
+            write_file(&downloads.join(name), 5);
+            set_age(&downloads.join(name), 30);
+        }
+
+        let mut report = scan(&options.inboxes[1], &options, SystemTime::now()).expect("scan");
+        assert_eq!(report.planned.len(), 2);
+        write_file(&downloads.join("growing.zip"), 10);
+        set_age(&downloads.join("growing.zip"), 30);
+        let journal = journal::Journal::start(&options.journal, Local::now());
+        apply_plan(&options, &journal, Local::now(), &mut report);
+
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, downloads.join("growing.zip"));
+        assert_eq!(report.skipped[0].reason, SkipReason::Changed);
+        assert_eq!(report.quarantined.len(), 1);
+        assert!(downloads.join("growing.zip").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_aged_by_their_own_modification_time() {
+        let dir = tempdir().expect("tempdir");
+        let mut options = options(dir.path());
+        options.grace_period = Duration::from_secs(60 * 60);
+        let downloads = dir.path().join("Downloads");
+        let target = dir.path().join("old.iso");
+        write_file(&target, 5);
+        set_age(&target, 30);
+        std::os::unix::fs::symlink(&target, downloads.join("old.iso")).expect("symlink");
+
+        let report = run(&options).expect("tidy run");
+        let down = inbox(&report, DOWNLOADS);
+        assert!(down.old_items.is_empty());
+        assert!(down.planned.is_empty());
+    }
+
+    #[test]
+    fn apply_continues_after_a_failed_item_and_records_outcomes() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let mut options = options(home);
+        options.apply = true;
+        let downloads = home.join("Downloads");
+        fs::create_dir_all(home.join("Documents")).expect("documents");
+        // A file where the rule's target folder should be.
+        write_file(&home.join("Documents/finance"), 1);
+
+        let spec = spec(
+            r#"{
+  "version": 1,
+  "areas": [{ "name": "Documents", "root": "~/Documents" }],
+  "rules": [{ "ext": ["pdf"], "action": "move-to Documents/finance" }]
+}"#,
+        );
+        options
+            .configure(&spec, &UserDirs::defaults(home))
+            .expect("configure");
+        write_file(&downloads.join("bill.pdf"), 5);
+        write_file(&downloads.join("old.txt"), 5);
+        set_age(&downloads.join("old.txt"), 30);
+
+        let report = run(&options).expect("tidy run");
+        let outcome = |name: &str| {
+            let down = inbox(&report, DOWNLOADS);
+            let planned = down.planned.iter().find(|p| p.path.ends_with(name));
+            planned.unwrap().outcome.clone().unwrap()
+        };
+        assert!(
+            matches!(outcome("bill.pdf"), Outcome::Failed { error } if error.contains("failed to create directory"))
+        );
+        assert_eq!(
+            outcome("old.txt"),
+            Outcome::Done {
+                journal_error: None,
+                manifest_error: None,
+            }
+        );
+        assert!(downloads.join("bill.pdf").exists());
+        assert!(!downloads.join("old.txt").exists());
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, &downloads.join("bill.pdf"));
+    }
+
+    #[test]
+    fn actions_that_cannot_be_journaled_still_count_as_done() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let mut options = options(home);
+        options.apply = true;
+        write_file(&home.join("not-a-dir"), 1);
+        options.journal = home.join("not-a-dir/journal.jsonl");
+        let downloads = home.join("Downloads");
+        write_file(&downloads.join("old.txt"), 5);
+        set_age(&downloads.join("old.txt"), 30);
+
+        let report = run(&options).expect("tidy run");
+        assert!(report.failures().next().is_none());
+        let unjournaled: Vec<_> = report.unjournaled().collect();
+        assert_eq!(unjournaled.len(), 1);
+        assert_eq!(unjournaled[0].0, &downloads.join("old.txt"));
+        assert!(unjournaled[0].1.contains("failed to create directory"));
+        assert!(!downloads.join("old.txt").exists());
+        assert_eq!(inbox(&report, DOWNLOADS).quarantined.len(), 1);
+    }
+
+    #[test]
+    fn absent_inboxes_are_reported_unless_required() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        fs::create_dir_all(home.join("Downloads")).expect("downloads");
+        let dirs = UserDirs::defaults(home);
+        let mut options = TidyOptions::new(&dirs);
+        options.apply = true;
+
+        let report = run(&options).expect("tidy run");
+        let desktop = inbox(&report, DESKTOP);
+        assert!(desktop.absent);
+        assert_eq!(desktop.status(), "absent");
+        assert!(!inbox(&report, DOWNLOADS).absent);
+
+        let required = spec(r#"{ "version": 1, "tidy": { "desktop": { "required": true } } }"#);
+        options.configure(&required, &dirs).expect("configure");
+        write_file(&home.join("Downloads/old.txt"), 5);
+        set_age(&home.join("Downloads/old.txt"), 30);
+        let report = run(&options).expect("tidy run");
+        let desktop = inbox(&report, DESKTOP);
+        assert_eq!(desktop.status(), "failed");
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, &home.join("Desktop"));
+        assert!(failures[0].1.contains("inbox `Desktop` is required"));
+        // The other inboxes are still tidied.
+        assert_eq!(inbox(&report, DOWNLOADS).quarantined.len(), 1);
+    }
+
+    #[test]
+    fn identical_files_at_the_destination_are_quarantined_not_copied() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let mut options = options(home);
+        options.apply = true;
+        let downloads = home.join("Downloads");
+        let finance = home.join("Documents/finance");
+        fs::create_dir_all(&finance).expect("finance");
+
+        let spec = spec(
+            r#"{
+  "version": 1,
+  "areas": [{ "name": "Documents", "root": "~/Documents" }],
+  "rules": [{ "ext": ["pdf"], "action": "move-to Documents/finance" }]
+}"#,
+        );
+        options
+            .configure(&spec, &UserDirs::defaults(home))
+            .expect("configure");
+        fs::write(downloads.join("same.pdf"), "invoice").expect("write");
+        fs::write(finance.join("same.pdf"), "invoice").expect("write");
+        fs::write(downloads.join("changed.pdf"), "invoice v2").expect("write");
+        fs::write(finance.join("changed.pdf"), "invoice v1").expect("write");
+
+        let report = run(&options).expect("tidy run");
+        let down = inbox(&report, DOWNLOADS);
+        let same = down
+            .planned
+            .iter()
+            .find(|p| p.path.ends_with("same.pdf"))
+            .unwrap();
+        assert_eq!(same.action, RuleAction::Quarantine);
+        assert_eq!(same.duplicate_of, Some(finance.join("same.pdf")));
+        assert_eq!(down.quarantined.len(), 1);
+        assert!(!downloads.join("same.pdf").exists());
+        assert!(!finance.join("same (1).pdf").exists());
+        assert_eq!(
+            fs::read_to_string(finance.join("changed (1).pdf")).unwrap(),
+            "invoice v2"
+        );
+    }
+
+    #[test]
+    fn rules_route_downloads_before_the_age_cutoff() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let mut options = options(home);
+        options.delete_mode = DeleteMode::Permanent;
+        let downloads = home.join("Downloads");
+
+        let spec = spec(
+            r#"{
+  "version": 1,
+  "areas": [{ "name": "Documents", "root": "~/Documents" }],
+  "rules": [
+    { "name": "invoices", "regex": "(?i)^invoice", "action": "move-to Documents/finance" },
+    { "ext": ["iso"], "action": "quarantine" },
+    { "ext": ["mp3"], "action": "keep" }
+  ]
+}"#,
+        );
+        options
+            .configure(&spec, &UserDirs::defaults(home))
+            .expect("configure");
+        for name in [
+            "Invoice-42.pdf",
+            "disk.iso",
+            "song.mp3",
+            "stale.txt",
+            "fresh.txt",
+        ] {
+            write_file(&downloads.join(name), 5);
+            if name != "fresh.txt" {
+                set_age(&downloads.join(name), 30);
+            }
+        }
+
+        let dry = run(&options).expect("dry run");
+        let mut plan: Vec<_> = inbox(&dry, DOWNLOADS)
+            .planned
+            .iter()
+            .map(|p| {
+                let name = p.path.file_name().unwrap().to_str().unwrap();
+                (name, p.rule.as_deref(), p.action)
+            })
+            .collect();
+        plan.sort_by_key(|(name, _, _)| *name);
+        assert_eq!(
+            plan,
+            vec![
+                ("Invoice-42.pdf", Some("`invoices`"), RuleAction::MoveTo),
+                ("disk.iso", Some("#2"), RuleAction::Quarantine),
+                ("song.mp3", Some("#3"), RuleAction::Keep),
+                ("stale.txt", None, RuleAction::Delete),
+            ]
+        );
+
+        options.apply = true;
+        let report = run(&options).expect("tidy run");
+        let down = inbox(&report, DOWNLOADS);
+        assert!(home.join("Documents/finance/Invoice-42.pdf").exists());
+        assert_eq!(down.moved.len(), 1);
+        assert_eq!(down.quarantined.len(), 1);
+        assert_eq!(down.deleted, vec![downloads.join("stale.txt")]);
+        assert!(downloads.join("song.mp3").exists());
+        assert!(downloads.join("fresh.txt").exists());
+        let ops: Vec<_> = journal::read(&options.journal)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.op)
+            .collect();
+        assert_eq!(ops.len(), 3);
+    }
+
+    #[test]
+    fn spec_inboxes_have_own_rules_retention_and_destination() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let mut options = options(home);
+        let scans = home.join("Scans");
+        fs::create_dir_all(&scans).expect("scans");
+        write_file(&scans.join("receipt.pdf"), 5);
+        write_file(&scans.join("page.tiff"), 5);
+        write_file(&scans.join("old.jpg"), 5);
+        set_age(&scans.join("old.jpg"), 3);
+
+        let spec = spec(
+            r#"{
+  "version": 1,
+  "areas": [{ "name": "Documents", "root": "~/Documents" }],
+  "tidy": {
+    "inboxes": [
+      {
+        "name": "scans",
+        "path": "~/Scans",
+        "rules": [{ "ext": ["pdf"], "action": "move-to Documents/scans" }],
+        "older_than": "2d",
+        "destination": "Documents/unsorted"
+      }
+    ]
+  }
+}"#,
+        );
+        options
+            .configure(&spec, &UserDirs::defaults(home))
+            .expect("configure");
+        options.select_inbox("SCANS").expect("select");
+        assert_eq!(options.inboxes.len(), 1);
+        assert!(options.select_inbox("music").is_err());
+
+        options.apply = true;
+        let report = run(&options).expect("tidy run");
+        assert_eq!(report.inboxes.len(), 1);
+        assert_eq!(report.inboxes[0].moved.len(), 2);
+        assert!(home.join("Documents/scans/receipt.pdf").exists());
+        assert!(home.join("Documents/unsorted/old.jpg").exists());
+        assert!(scans.join("page.tiff").exists());
+
+        let dirs = UserDirs::defaults(home);
+        let mut twice = TidyOptions::new(&dirs);
+        let duplicate: SpecFile = serde_json::from_str(
+            r#"{ "version": 1, "tidy": { "inboxes": [{ "name": "downloads", "path": "~/x" }] } }"#,
+        )
+        .expect("spec");
+        assert!(twice.configure(&duplicate, &dirs).is_err());
+    }
+
+    #[test]
+    fn default_inboxes_follow_xdg_user_dirs() {
+        let home = Path::new("/home/u");
+        let dirs = UserDirs::resolve(
+            home,
+            Some("XDG_DESKTOP_DIR=\"$HOME/Työpöytä\"\nXDG_DOCUMENTS_DIR=\"$HOME/Asiakirjat\""),
+            |name| (name == "XDG_DOWNLOAD_DIR").then(|| "/data/inbox".to_string()),
+        );
+        let options = TidyOptions::new(&dirs);
+
+        assert_eq!(options.inboxes[0].path, home.join("Työpöytä"));
+        assert_eq!(
+            options.inboxes[0].rules[0].destination.as_deref(),
+            Some(home.join("Asiakirjat/screenshots").as_path())
+        );
+        assert_eq!(options.inboxes[1].path, Path::new("/data/inbox"));
     }
 }
@@ -0,0 +1,286 @@
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::journal::{self, Entry, Journal, Operation};
+use crate::transfer;
+use crate::trash;
+
+#[derive(Debug)]
+pub struct UndoReport {
+    /// The run being reversed.
+    pub run_id: String,
+    pub applied: bool,
+    /// Items moved (or, in a dry run, to be moved) back: `(from, to)`.
+    pub restored: Vec<(PathBuf, PathBuf)>,
+    /// Items already back at their original location.
+    pub already_restored: Vec<PathBuf>,
+    pub conflicts: Vec<Conflict>,
+    /// Items that could not be moved back, with the error. They stay where
+    /// the run put them.
+    pub failed: Vec<(PathBuf, String)>,
+    /// Restored items whose `restore` line could not be journaled, with the
+    /// error.
+    pub unjournaled: Vec<(PathBuf, String)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The item's original location.
+    pub path: PathBuf,
+    pub reason: ConflictReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictReason {
+    /// Something new now sits at the original location.
+    Reoccupied,
+    /// The moved item is gone (purged, or moved again by hand).
+    Gone,
+    /// The run deleted it permanently (`--delete-mode permanent`).
+    Deleted,
+}
+
+/// Reverses `run_id`, or the latest run that has not been undone yet.
+/// Operations are replayed newest first and nothing is ever overwritten.
+/// An item that fails to move back is recorded and the rest are still tried.
+pub fn run(journal_path: &Path, run_id: Option<&str>, apply: bool) -> Result<UndoReport> {
+    let entries = journal::read(journal_path)?;
+    let run_id = match run_id {
+        Some(id) => {
+            if !entries.iter().any(|e| e.run_id == id) {
+                bail!("no run `{id}` in {}", journal_path.display());
+            }
+            id.to_string()
+        }
+        None => latest_undoable(&entries)
+            .with_context(|| format!("nothing to undo in {}", journal_path.display()))?,
+    };
+
+    let mut report = UndoReport {
+        run_id: run_id.clone(),
+        applied: apply,
+        restored: Vec::new(),
+        already_restored: Vec::new(),
+        conflicts: Vec::new(),
+        failed: Vec::new(),
+        unjournaled: Vec::new(),
+    };
+    let journal = Journal::start(journal_path, Local::now());
+
+    for entry in entries.iter().rev().filter(|e| e.run_id == run_id) {
+        let original = &entry.source;
+        let Some(moved) = &entry.destination else {
+            report.conflicts.push(Conflict {
+                path: original.clone(),
+                reason: ConflictReason::Deleted,
+            });
+            continue;
+        };
+        let original_taken = fs::symlink_metadata(original).is_ok();
+        let moved_present = fs::symlink_metadata(moved).is_ok();
+
+        match (original_taken, moved_present) {
+            (true, false) => report.already_restored.push(original.clone()),
+            (true, true) => report.conflicts.push(Conflict {
+                path: original.clone(),
+                reason: ConflictReason::Reoccupied,
+            }),
+            (false, false) => report.conflicts.push(Conflict {
+                path: original.clone(),
+                reason: ConflictReason::Gone,
+            }),
+            (false, true) => {
+                if apply {
+                    if let Err(err) = restore(moved, original) {
+                        report.failed.push((original.clone(), format!("{err:#}")));
+                        continue;
+                    }
+                    if entry.op == Operation::Trash
+                        && let Some(info) = trash::info_path_for(moved)
+                    {
+                        // Otherwise file managers would list a ghost entry.
+                        let _ = fs::remove_file(info);
+                    }
+                    // The item is back either way; a later undo of this run
+                    // finds it already restored.
+                    if let Err(err) = journal.record(
+                        Operation::Restore,
+                        moved,
+                        Some(original),
+                        entry.size,
+                        Some(&run_id),
+                    ) {
+                        report
+                            .unjournaled
+                            .push((original.clone(), format!("{err:#}")));
+                    }
+                }
+                report.restored.push((moved.clone(), original.clone()));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// The newest run that is not itself an undo and has not been undone.
+fn latest_undoable(entries: &[Entry]) -> Option<String> {
+    entries
+        .iter()
+        .rev()
+        .filter(|e| e.op != Operation::Restore)
+        .map(|e| &e.run_id)
+        .find(|id| !entries.iter().any(|e| e.undoes.as_ref() == Some(*id)))
+        .cloned()
+}
+
+fn restore(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory: {}", parent.display()))?;
+    }
+    transfer::move_path(from, to)
+        .with_context(|| format!("failed to restore {} -> {}", from.display(), to.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Conflict, ConflictReason, run};
+    use crate::journal::{Journal, Operation};
+    use chrono::{Local, TimeZone};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn undo_restores_latest_run_and_reports_conflicts() {
+        let dir = tempdir().expect("tempdir");
+        let base = dir.path();
+        let journal_path = base.join("state/journal.jsonl");
+        fs::create_dir_all(base.join("moved")).expect("moved dir");
+
+        // An earlier run that was already undone must not be picked again.
+        let old = Journal::start(
+            &journal_path,
+            Local.with_ymd_and_hms(2026, 10, 1, 9, 0, 0).unwrap(),
+        );
+        old.record(
+            Operation::Move,
+            &base.join("x"),
+            Some(&base.join("moved/x")),
+            1,
+            None,
+        )
+        .unwrap();
+        let old_undo = Journal::start(
+            &journal_path,
+            Local.with_ymd_and_hms(2026, 10, 2, 9, 0, 0).unwrap(),
+        );
+        old_undo
+            .record(
+                Operation::Restore,
+                &base.join("moved/x"),
+                Some(&base.join("x")),
+                1,
+                Some(old.run_id()),
+            )
+            .unwrap();
+
+        let journal = Journal::start(
+            &journal_path,
+            Local.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap(),
+        );
+        for name in ["a", "b", "c"] {
+            journal
+                .record(
+                    Operation::Quarantine,
+                    &base.join(name),
+                    Some(&base.join("moved").join(name)),
+                    1,
+                    None,
+                )
+                .unwrap();
+        }
+        journal
+            .record(Operation::Delete, &base.join("d"), None, 1, None)
+            .unwrap();
+        fs::write(base.join("moved/a"), "a").unwrap();
+        fs::write(base.join("moved/b"), "b").unwrap();
+        fs::write(base.join("b"), "new b").unwrap();
+
+        let dry = run(&journal_path, None, false).expect("dry run");
+        assert_eq!(dry.run_id, journal.run_id());
+        assert_eq!(dry.restored.len(), 1);
+        assert!(base.join("moved/a").exists());
+
+        let report = run(&journal_path, None, true).expect("undo");
+        assert_eq!(
+            report.restored,
+            vec![(base.join("moved/a"), base.join("a"))]
+        );
+        assert_eq!(
+            report.conflicts,
+            vec![
+                Conflict {
+                    path: base.join("d"),
+                    reason: ConflictReason::Deleted,
+                },
+                Conflict {
+                    path: base.join("c"),
+                    reason: ConflictReason::Gone,
+                },
+                Conflict {
+                    path: base.join("b"),
+                    reason: ConflictReason::Reoccupied,
+                },
+            ]
+        );
+        assert_eq!(fs::read_to_string(base.join("a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(base.join("b")).unwrap(), "new b");
+
+        // Running it again is harmless.
+        let again = run(&journal_path, Some(journal.run_id()), true).expect("undo again");
+        assert!(again.restored.is_empty());
+        assert_eq!(again.already_restored, vec![base.join("a")]);
+        assert!(run(&journal_path, Some("nope"), false).is_err());
+    }
+
+    #[test]
+    fn undo_keeps_going_after_an_item_fails() {
+        let dir = tempdir().expect("tempdir");
+        let base = dir.path();
+        let journal_path = base.join("journal.jsonl");
+        fs::create_dir_all(base.join("moved")).expect("moved dir");
+        // `blocked/a` cannot be recreated: `blocked` is a file.
+        fs::write(base.join("blocked"), "").unwrap();
+        let journal = Journal::start(
+            &journal_path,
+            Local.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap(),
+        );
+        for (original, moved) in [("b", "moved/b"), ("blocked/a", "moved/a")] {
+            fs::write(base.join(moved), original).unwrap();
+            journal
+                .record(
+                    Operation::Move,
+                    &base.join(original),
+                    Some(&base.join(moved)),
+                    1,
+                    None,
+                )
+                .unwrap();
+        }
+
+        let report = run(&journal_path, None, true).expect("undo");
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, base.join("blocked/a"));
+        assert!(report.failed[0].1.contains("failed to create directory"));
+        assert!(base.join("moved/a").exists());
+        assert_eq!(
+            report.restored,
+            vec![(base.join("moved/b"), base.join("b"))]
+        );
+        assert!(report.unjournaled.is_empty());
+        assert_eq!(fs::read_to_string(base.join("b")).unwrap(), "b");
+    }
+}
@@ -0,0 +1,214 @@
+//! Sizes of files and folder trees. Symlinks are measured as links and never
+//! followed, so a link to `/` or to a parent folder cannot make a scan walk
+//! the whole disk or loop. Hard-linked files and folders reached twice (bind
+//! mounts) are counted once.
+
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::ops::AddAssign;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    /// Sum of file lengths.
+    pub apparent: u64,
+    /// Disk blocks in use: less for sparse or compressed files, more for
+    /// many small ones.
+    pub allocated: u64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.apparent = self.apparent.saturating_add(other.apparent);
+        self.allocated = self.allocated.saturating_add(other.allocated);
+    }
+}
+
+/// Measures several items, remembering what it has counted.
+#[derive(Debug, Default)]
+pub struct Tally {
+    /// Only entries on this filesystem are counted (mount points are not
+    /// entered).
+    device: Option<u64>,
+    seen: HashSet<(u64, u64)>,
+}
+
+impl Tally {
+    /// Stays on the filesystem of `root` (followed if it is a symlink), the
+    /// folder the measured items live in.
+    pub fn within(root: &Path) -> Self {
+        Self {
+            device: fs::metadata(root).ok().as_ref().and_then(device),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// The usage of `path` on its own, and the part of it no earlier call
+    /// has counted (what it adds to a total).
+    pub fn measure(&mut self, path: &Path) -> (Usage, Usage) {
+        let Ok(meta) = fs::symlink_metadata(path) else {
+            return Default::default();
+        };
+        let mut walk = Walk {
+            device: self.device,
+            own: HashSet::new(),
+            seen: &mut self.seen,
+            usage: (Usage::default(), Usage::default()),
+        };
+        walk.visit(path, &meta, true);
+        walk.usage
+    }
+}
+
+/// Apparent size of `path`, symlinks not followed.
+pub fn size(path: &Path) -> u64 {
+    Tally::default().measure(path).0.apparent
+}
+
+struct Walk<'a> {
+    device: Option<u64>,
+    /// Inodes counted for this item.
+    own: HashSet<(u64, u64)>,
+    /// Inodes counted for any item.
+    seen: &'a mut HashSet<(u64, u64)>,
+    usage: (Usage, Usage),
+}
+
+impl Walk<'_> {
+    /// `fresh`: no earlier item reached this folder.
+    fn visit(&mut self, path: &Path, meta: &Metadata, fresh: bool) {
+        if self.device.is_some() && device(meta) != self.device {
+            return;
+        }
+        let id = inode(meta);
+        let shared = meta.is_dir() || link_count(meta) > 1;
+        let (first_here, first_anywhere) = match id {
+            Some(id) if shared => (self.own.insert(id), self.seen.insert(id)),
+            _ => (true, true),
+        };
+        let fresh = fresh && first_anywhere;
+        if !first_here {
+            return;
+        }
+
+        if meta.is_dir() {
+            let Ok(entries) = fs::read_dir(path) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                // `DirEntry::metadata` does not follow symlinks.
+                if let Ok(meta) = entry.metadata() {
+                    self.visit(&entry.path(), &meta, fresh);
+                }
+            }
+        } else {
+            let usage = Usage {
+                apparent: meta.len(),
+                allocated: allocated(meta),
+            };
+            self.usage.0 += usage;
+            if fresh {
+                self.usage.1 += usage;
+            }
+        }
+    }
+}
+
+/// `(dev, ino)`, where the platform has them.
+#[cfg(unix)]
+pub fn inode(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(unix)]
+fn device(meta: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.dev())
+}
+
+#[cfg(unix)]
+pub fn link_count(meta: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.nlink()
+}
+
+#[cfg(unix)]
+fn allocated(meta: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // `st_blocks` is in 512-byte units whatever the filesystem block size.
+    meta.blocks().saturating_mul(512)
+}
+
+#[cfg(not(unix))]
+pub fn inode(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(not(unix))]
+fn device(_meta: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn link_count(_meta: &Metadata) -> u64 {
+    1
+}
+
+#[cfg(not(unix))]
+fn allocated(meta: &Metadata) -> u64 {
+    meta.len()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{Tally, size};
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use tempfile::tempdir;
+
+    #[test]
+    fn symlinks_are_not_followed_and_hard_links_count_once() {
+        let dir = tempdir().expect("tempdir");
+        let album = dir.path().join("album");
+        fs::create_dir_all(album.join("disc")).expect("dirs");
+        fs::write(album.join("disc/track"), vec![b'a'; 10_000]).expect("write");
+        fs::hard_link(album.join("disc/track"), album.join("copy")).expect("hard link");
+        // A loop and a link to the whole filesystem.
+        symlink("..", album.join("disc/up")).expect("symlink");
+        symlink("/", album.join("root")).expect("symlink");
+        fs::write(dir.path().join("other"), "x").expect("write");
+        fs::hard_link(album.join("copy"), dir.path().join("again")).expect("hard link");
+
+        let links = ("..".len() + "/".len()) as u64;
+        assert_eq!(size(&album), 10_000 + links);
+        assert_eq!(size(&album.join("root")), 1);
+
+        let mut tally = Tally::within(dir.path());
+        let (own, new) = tally.measure(&album);
+        assert_eq!(own, new);
+        assert!(own.allocated >= 8192);
+        let (own, new) = tally.measure(&dir.path().join("again"));
+        assert_eq!(own.apparent, 10_000);
+        assert_eq!(new.apparent, 0);
+        let (own, new) = tally.measure(&dir.path().join("other"));
+        assert_eq!((own.apparent, new.apparent), (1, 1));
+    }
+
+    #[test]
+    fn one_filesystem_skips_entries_on_other_devices() {
+        let dir = tempdir().expect("tempdir");
+        fs::create_dir_all(dir.path().join("mnt")).expect("dir");
+        fs::write(dir.path().join("mnt/disk.img"), "image").expect("write");
+
+        let (own, _) = Tally::within(dir.path()).measure(&dir.path().join("mnt"));
+        assert_eq!(own.apparent, 5);
+        // As if `mnt` were a mount point of another filesystem.
+        let mut elsewhere = Tally {
+            device: Some(u64::MAX),
+            ..Tally::default()
+        };
+        let (own, new) = elsewhere.measure(&dir.path().join("mnt"));
+        assert_eq!((own.apparent, new.apparent), (0, 0));
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+/// Parses ages like `30d`, `12h`, `2w` or `90m` (a bare number is days).
+/// Used as a clap value parser, hence the `String` error.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration `{input}` (expected e.g. 30d, 12h, 2w)"))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit `{unit}` in `{input}` (use s, m, h, d or w)"
+            ));
+        }
+    };
+    Ok(Duration::from_secs(value.saturating_mul(seconds)))
+}
+
+/// The inverse of `parse_duration`, using the largest whole unit (`14d`).
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    for (unit, size) in [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60)] {
+        if secs >= size && secs.is_multiple_of(size) {
+            return format!("{}{unit}", secs / size);
+        }
+    }
+    format!("{secs}s")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, parse_duration};
+    use std::time::Duration;
+
+    #[test]
+    fn parse_duration_accepts_units() {
+        const DAY: u64 = 24 * 60 * 60;
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * DAY)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * DAY)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("7"), Ok(Duration::from_secs(7 * DAY)));
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+    }
+
+    #[test]
+    fn format_duration_round_trips() {
+        for input in ["14d", "36h", "90m", "45s", "0s"] {
+            assert_eq!(format_duration(parse_duration(input).unwrap()), input);
+        }
+        assert_eq!(format_duration(parse_duration("2w").unwrap()), "14d");
+    }
+}
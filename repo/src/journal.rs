@@ -0,0 +1,160 @@
+//! Append-only record of every file operation `tidy --apply` performs, so a
+//! run can be reversed with `life-os undo`. One JSON object per line in
+//! `System/life-os/state/journal.jsonl`; lines are never rewritten.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+pub fn default_path(home: &Path) -> PathBuf {
+    home.join("System/life-os/state").join(JOURNAL_FILE)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// A screenshot (or other item) moved to its destination folder.
+    Move,
+    /// A download moved into the quarantine.
+    Quarantine,
+    /// A download moved to the freedesktop Trash.
+    Trash,
+    /// A download removed for good (cannot be undone).
+    Delete,
+    /// An item put back by `life-os undo`.
+    Restore,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub run_id: String,
+    pub op: Operation,
+    pub source: PathBuf,
+    /// Where the item went; `None` for `delete`.
+    pub destination: Option<PathBuf>,
+    pub size: u64,
+    /// RFC 3339 time of the operation.
+    pub timestamp: String,
+    /// For `restore` entries: the run being undone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<String>,
+}
+
+/// Writes entries for a single run.
+pub struct Journal {
+    path: PathBuf,
+    run_id: String,
+}
+
+impl Journal {
+    pub fn start(path: &Path, now: DateTime<Local>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            run_id: format!("{}-{}", now.format("%Y%m%d-%H%M%S"), std::process::id()),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Appends one operation. Call after the operation succeeded.
+    pub fn record(
+        &self,
+        op: Operation,
+        source: &Path,
+        destination: Option<&Path>,
+        size: u64,
+        undoes: Option<&str>,
+    ) -> Result<()> {
+        let entry = Entry {
+            run_id: self.run_id.clone(),
+            op,
+            source: source.to_path_buf(),
+            destination: destination.map(Path::to_path_buf),
+            size,
+            timestamp: Local::now().to_rfc3339(),
+            undoes: undoes.map(str::to_string),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory: {}", parent.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open journal: {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .with_context(|| format!("failed to write journal: {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Reads every entry, oldest first. A missing journal is empty.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read journal: {}", path.display()));
+        }
+    };
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: invalid journal entry", path.display(), index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Journal, Operation, read};
+    use chrono::{Local, TimeZone};
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn journal_appends_entries_per_run() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state/journal.jsonl");
+        assert!(read(&path).unwrap().is_empty());
+
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 10, 15, 0).unwrap();
+        let journal = Journal::start(&path, now);
+        assert!(journal.run_id().starts_with("20261016-101500-"));
+        journal
+            .record(
+                Operation::Move,
+                Path::new("/a"),
+                Some(Path::new("/b")),
+                3,
+                None,
+            )
+            .unwrap();
+        journal
+            .record(
+                Operation::Restore,
+                Path::new("/b"),
+                Some(Path::new("/a")),
+                3,
+                Some("x"),
+            )
+            .unwrap();
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].run_id, journal.run_id());
+        assert_eq!(entries[0].op, Operation::Move);
+        assert_eq!(entries[0].undoes, None);
+        assert_eq!(entries[1].undoes.as_deref(), Some("x"));
+    }
+}
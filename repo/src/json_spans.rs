@@ -0,0 +1,423 @@
+//! Minimal JSON reader that keeps line/column positions for every value and
+//! object key. Only used by `spec lint`; loading goes through serde.
+//! Positions are 1-based; `0:0` means unknown.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Spanned {
+    pub pos: Pos,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool,
+    /// Raw number text, e.g. `1` or `1.5`.
+    Number(String),
+    String(String),
+    Array(Vec<Spanned>),
+    Object(Vec<Member>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub key: String,
+    pub key_pos: Pos,
+    pub value: Spanned,
+}
+
+impl Spanned {
+    pub fn as_object(&self) -> Option<&[Member]> {
+        match &self.value {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Spanned]> {
+        match &self.value {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Spanned> {
+        self.as_object()?
+            .iter()
+            .find(|m| m.key == key)
+            .map(|m| &m.value)
+    }
+}
+
+/// Builds a tree without positions (all `Pos` are `0:0`), for specs that
+/// were not written as JSON.
+pub fn from_value(value: &serde_json::Value) -> Spanned {
+    let value = match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(_) => Value::Bool,
+        serde_json::Value::Number(n) => Value::Number(n.to_string()),
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        serde_json::Value::Array(items) => Value::Array(items.iter().map(from_value).collect()),
+        serde_json::Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| Member {
+                    key: key.clone(),
+                    key_pos: Pos::default(),
+                    value: from_value(value),
+                })
+                .collect(),
+        ),
+    };
+    Spanned {
+        pos: Pos::default(),
+        value,
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub pos: Pos,
+    pub message: String,
+}
+
+pub fn parse(input: &str) -> Result<Spanned, ParseError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        index: 0,
+        line: 1,
+        column: 1,
+    };
+    parser.skip_ws();
+    let value = parser.value()?;
+    parser.skip_ws();
+    if parser.peek().is_some() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn pos(&self) -> Pos {
+        Pos {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            pos: self.pos(),
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected `{expected}`"))),
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self) -> Result<Spanned, ParseError> {
+        let pos = self.pos();
+        let value = match self.peek() {
+            Some('{') => self.object()?,
+            Some('[') => self.array()?,
+            Some('"') => Value::String(self.string()?),
+            Some('t') => self.keyword("true", Value::Bool)?,
+            Some('f') => self.keyword("false", Value::Bool)?,
+            Some('n') => self.keyword("null", Value::Null)?,
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+            _ => return Err(self.error("expected a value")),
+        };
+        Ok(Spanned { pos, value })
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, ParseError> {
+        let start = self.pos();
+        for expected in word.chars() {
+            if self.bump() != Some(expected) {
+                return Err(ParseError {
+                    pos: start,
+                    message: format!("expected `{word}`"),
+                });
+            }
+        }
+        Ok(value)
+    }
+
+    /// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`, so `01`, `1.`
+    /// and `.5` are rejected like serde does.
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let invalid = ParseError {
+            pos: self.pos(),
+            message: "invalid number".to_string(),
+        };
+        let mut raw = String::new();
+        self.take(&mut raw, |c| c == '-');
+        let leading_zero = self.peek() == Some('0');
+        if self.digits(&mut raw) == 0 || (leading_zero && raw.trim_start_matches('-') != "0") {
+            return Err(invalid);
+        }
+        if self.take(&mut raw, |c| c == '.') && self.digits(&mut raw) == 0 {
+            return Err(invalid);
+        }
+        if self.take(&mut raw, |c| matches!(c, 'e' | 'E')) {
+            self.take(&mut raw, |c| matches!(c, '+' | '-'));
+            if self.digits(&mut raw) == 0 {
+                return Err(invalid);
+            }
+        }
+        Ok(Value::Number(raw))
+    }
+
+    /// Moves the next character to `raw` if it is accepted.
+    fn take(&mut self, raw: &mut String, accept: impl Fn(char) -> bool) -> bool {
+        match self.peek() {
+            Some(c) if accept(c) => {
+                raw.push(c);
+                self.bump();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn digits(&mut self, raw: &mut String) -> usize {
+        let mut count = 0;
+        while self.take(raw, |c| c.is_ascii_digit()) {
+            count += 1;
+        }
+        count
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(out),
+                Some('\\') => match self.bump() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('u') => out.push(self.unicode_escape()?),
+                    Some(c @ ('"' | '\\' | '/')) => out.push(c),
+                    Some(_) => return Err(self.error("invalid escape")),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) => out.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// The character after `\u`. A UTF-16 surrogate pair is two escapes
+    /// (`\uD83D\uDE00`) for one character.
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("unpaired surrogate"));
+        }
+        if self.bump() != Some('\\') || self.bump() != Some('u') {
+            return Err(self.error("unpaired surrogate"));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate"));
+        }
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .bump()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Value::Array(items));
+        }
+        loop {
+            self.skip_ws();
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, ParseError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_ws();
+            let key_pos = self.pos();
+            let key = self.string()?;
+            self.skip_ws();
+            self.expect(':')?;
+            self.skip_ws();
+            let value = self.value()?;
+            members.push(Member {
+                key,
+                key_pos,
+                value,
+            });
+            self.skip_ws();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pos, Value, parse};
+
+    #[test]
+    fn parse_tracks_key_and_value_positions() {
+        let doc = parse("{\n  \"version\": 1,\n  \"areas\": []\n}").expect("parse");
+
+        let members = doc.as_object().expect("object");
+        assert_eq!(members[0].key, "version");
+        assert_eq!(members[0].key_pos, Pos { line: 2, column: 3 });
+        assert_eq!(
+            members[1].value.pos,
+            Pos {
+                line: 3,
+                column: 12
+            }
+        );
+    }
+
+    #[test]
+    fn parse_reports_error_position() {
+        let err = parse("{\n  \"a\": tru\n}").expect_err("invalid");
+        assert_eq!(err.pos.line, 2);
+    }
+
+    #[test]
+    fn parse_joins_surrogate_pairs_and_counts_source_columns() {
+        let doc = parse(r#"{"\uD83D\uDE00é": 1, "b": "\u00e9"}"#).expect("parse");
+        let members = doc.as_object().expect("object");
+        assert_eq!(members[0].key, "😀é");
+        // The escape is 12 characters of source text.
+        assert_eq!(
+            members[1].key_pos,
+            Pos {
+                line: 1,
+                column: 22
+            }
+        );
+        assert_eq!(members[1].value.as_str(), Some("é"));
+
+        for bad in [
+            r#""\uD83D""#,
+            r#""\uD83Dx""#,
+            r#""\uDE00""#,
+            r#""\uD83D\u0041""#,
+        ] {
+            assert_eq!(parse(bad).expect_err(bad).message, "unpaired surrogate");
+        }
+        assert_eq!(
+            parse(r#""\x""#).expect_err("escape").message,
+            "invalid escape"
+        );
+    }
+
+    #[test]
+    fn parse_follows_the_json_number_grammar() {
+        for good in ["0", "-0", "10", "1.5", "-0.25e-3", "2E+10", "1e5"] {
+            let doc = parse(good).expect(good);
+            assert!(
+                matches!(doc.value, Value::Number(ref raw) if raw == good),
+                "{good}"
+            );
+        }
+        for bad in [
+            "01", "-01", "1.", "1.e5", "-", "1e", "1e+", "+1", ".5", "--1",
+        ] {
+            assert!(parse(bad).is_err(), "{bad}");
+        }
+        let err = parse("[1, 02]").expect_err("leading zero");
+        assert_eq!(
+            (err.pos.column, err.message.as_str()),
+            (5, "invalid number")
+        );
+    }
+}
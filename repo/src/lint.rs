@@ -0,0 +1,767 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::duration::parse_duration;
+use crate::json_spans::{self, Pos, Spanned, Value};
+use crate::rules::{parse_action, parse_size};
+use crate::spec::{CURRENT_VERSION, SpecFile, rule_label};
+use crate::spec_loader::{SpecFormat, expand_root};
+
+const TOP_KEYS: &[&str] = &[
+    "version", "include", "hosts", "vars", "areas", "rules", "tidy",
+];
+const HOST_KEYS: &[&str] = &["include", "vars", "areas", "tidy"];
+const AREA_KEYS: &[&str] = &["name", "root", "required", "strict", "allow_extra"];
+const NODE_KEYS: &[&str] = &["path", "children", "strict", "allow_extra", "symlink"];
+const RULE_KEYS: &[&str] = &[
+    "name",
+    "ext",
+    "glob",
+    "regex",
+    "larger_than",
+    "smaller_than",
+    "older_than",
+    "newer_than",
+    "action",
+];
+const TIDY_KEYS: &[&str] = &[
+    "grace_period",
+    "one_filesystem",
+    "desktop",
+    "downloads",
+    "inboxes",
+];
+const DESKTOP_KEYS: &[&str] = &[
+    "path",
+    "required",
+    "screenshots",
+    "screenshot_globs",
+    "screenshot_regexes",
+    "busy_screenshots",
+    "busy_other",
+];
+const DOWNLOADS_KEYS: &[&str] = &[
+    "path",
+    "required",
+    "older_than",
+    "moderate_above",
+    "heavy_above",
+    "many_items",
+];
+const INBOX_KEYS: &[&str] = &[
+    "name",
+    "path",
+    "required",
+    "rules",
+    "older_than",
+    "destination",
+    "moderate_above",
+    "heavy_above",
+    "many_items",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// A single lint finding. `code` is stable and meant for CI filters.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub pos: Option<Pos>,
+}
+
+impl Diagnostic {
+    fn error(code: &'static str, pos: Pos, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message,
+            pos: known(pos),
+        }
+    }
+
+    fn warning(code: &'static str, pos: Pos, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            message,
+            pos: known(pos),
+        }
+    }
+}
+
+fn known(pos: Pos) -> Option<Pos> {
+    (pos.line > 0).then_some(pos)
+}
+
+pub fn lint_spec(raw: &str, format: SpecFormat, home: Option<&Path>) -> Vec<Diagnostic> {
+    // JSON gets exact positions; other formats are linted structurally.
+    let doc = match format {
+        SpecFormat::Json => json_spans::parse(raw).map_err(|err| (err.pos, err.message)),
+        _ => format
+            .parse::<serde_json::Value>(raw)
+            .map(|value| json_spans::from_value(&value))
+            .map_err(|err| (error_pos(&err, raw), format!("{err}"))),
+    };
+    let doc = match doc {
+        Ok(doc) => doc,
+        Err((pos, message)) => {
+            return vec![Diagnostic::error("parse-error", pos, message)];
+        }
+    };
+
+    let mut out = Vec::new();
+    lint_document(&doc, home, &mut out);
+
+    if let Err(err) = format.parse::<SpecFile>(raw) {
+        let pos = error_pos(&err, raw);
+        let message = match err.downcast_ref::<toml::de::Error>() {
+            Some(toml_err) => toml_err.message().to_string(),
+            None => format!("{err}"),
+        };
+        out.push(Diagnostic::error("invalid-spec", pos, message));
+    }
+
+    out.sort_by_key(|d| (d.pos.unwrap_or_default(), d.severity));
+    out
+}
+
+fn error_pos(err: &anyhow::Error, raw: &str) -> Pos {
+    if let Some(err) = err.downcast_ref::<serde_json::Error>() {
+        return Pos {
+            line: err.line(),
+            column: err.column(),
+        };
+    }
+    if let Some(location) = err
+        .downcast_ref::<serde_norway::Error>()
+        .and_then(serde_norway::Error::location)
+    {
+        return Pos {
+            line: location.line(),
+            column: location.column(),
+        };
+    }
+    if let Some(span) = err
+        .downcast_ref::<toml::de::Error>()
+        .and_then(toml::de::Error::span)
+    {
+        let before = &raw[..span.start.min(raw.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+        return Pos { line, column };
+    }
+    Pos::default()
+}
+
+fn lint_document(doc: &Spanned, home: Option<&Path>, out: &mut Vec<Diagnostic>) {
+    if doc.as_object().is_none() {
+        out.push(Diagnostic::error(
+            "invalid-spec",
+            doc.pos,
+            "spec must be an object".to_string(),
+        ));
+        return;
+    }
+    unknown_keys(doc, TOP_KEYS, "spec", out);
+
+    if let Some(version) = doc.get("version") {
+        let known = match &version.value {
+            Value::Number(raw) => raw
+                .parse::<u32>()
+                .is_ok_and(|v| (1..=CURRENT_VERSION).contains(&v)),
+            _ => false,
+        };
+        if !known {
+            out.push(Diagnostic::error(
+                "unknown-version",
+                version.pos,
+                format!("unknown spec version (supported: 1..={CURRENT_VERSION})"),
+            ));
+        }
+    }
+
+    if let Some(areas) = doc.get("areas").and_then(Spanned::as_array) {
+        lint_areas(areas, home, out);
+    }
+    if let Some(rules) = doc.get("rules").and_then(Spanned::as_array) {
+        lint_rules(rules, out);
+    }
+    if let Some(tidy) = doc.get("tidy") {
+        lint_tidy(tidy, out);
+    }
+
+    for host in doc
+        .get("hosts")
+        .and_then(Spanned::as_object)
+        .unwrap_or_default()
+    {
+        unknown_keys(&host.value, HOST_KEYS, "host", out);
+        if let Some(tidy) = host.value.get("tidy") {
+            lint_tidy(tidy, out);
+        }
+        if let Some(areas) = host.value.get("areas").and_then(Spanned::as_array) {
+            lint_areas(areas, home, out);
+        }
+    }
+}
+
+fn lint_areas(areas: &[Spanned], home: Option<&Path>, out: &mut Vec<Diagnostic>) {
+    let mut roots: Vec<(PathBuf, &str)> = Vec::new();
+    for area in areas {
+        if area.as_object().is_none() {
+            continue;
+        }
+        unknown_keys(area, AREA_KEYS, "area", out);
+
+        let name = area.get("name").and_then(Spanned::as_str).unwrap_or("");
+        if let Some(value) = area.get("name")
+            && name.trim().is_empty()
+        {
+            out.push(Diagnostic::error(
+                "empty-name",
+                value.pos,
+                "area name is empty".to_string(),
+            ));
+        }
+
+        if let Some(value) = area.get("root")
+            && let Some(root) = value.as_str()
+        {
+            if root.trim().is_empty() {
+                out.push(Diagnostic::error(
+                    "empty-path",
+                    value.pos,
+                    format!("area `{name}` has an empty root"),
+                ));
+            } else {
+                let expanded = match home {
+                    Some(home) => expand_root(root, home),
+                    None => PathBuf::from(root),
+                };
+                for (other, other_name) in &roots {
+                    if *other == expanded {
+                        out.push(Diagnostic::error(
+                            "duplicate-root",
+                            value.pos,
+                            format!("area `{name}` has the same root as area `{other_name}`"),
+                        ));
+                    } else if expanded.starts_with(other) || other.starts_with(&expanded) {
+                        out.push(Diagnostic::warning(
+                            "nested-root",
+                            value.pos,
+                            format!("area `{name}` root overlaps area `{other_name}`"),
+                        ));
+                    }
+                }
+                roots.push((expanded, name));
+            }
+        }
+
+        lint_allow_extra(area, out);
+        if let Some(nodes) = area.get("required").and_then(Spanned::as_array) {
+            lint_nodes(nodes, name, out);
+        }
+    }
+}
+
+fn lint_nodes(nodes: &[Spanned], area: &str, out: &mut Vec<Diagnostic>) {
+    // As written, and with `.` and empty segments removed.
+    let mut seen: Vec<(&str, String)> = Vec::new();
+    for node in nodes {
+        // Nodes are either `{ "path": ... }` objects or bare path strings.
+        let path_value = if node.as_str().is_some() {
+            Some(node)
+        } else if node.as_object().is_some() {
+            unknown_keys(node, NODE_KEYS, "node", out);
+            node.get("path")
+        } else {
+            continue;
+        };
+
+        if let Some(value) = path_value
+            && let Some(path) = value.as_str()
+        {
+            let normalized = path.trim_end_matches('/');
+            if normalized.trim().is_empty() {
+                out.push(Diagnostic::error(
+                    "empty-path",
+                    value.pos,
+                    format!("node in area `{area}` has an empty path"),
+                ));
+            } else if normalized.starts_with('/') || normalized.starts_with('~') {
+                out.push(Diagnostic::error(
+                    "absolute-path",
+                    value.pos,
+                    format!("node path `{path}` must be relative to its parent"),
+                ));
+            } else if escapes_parent(normalized) {
+                out.push(Diagnostic::error(
+                    "escaping-path",
+                    value.pos,
+                    format!("node path `{path}` escapes its parent with `..`"),
+                ));
+            }
+
+            if !normalized.is_empty() {
+                let same = same_folder(path);
+                if seen.iter().any(|(written, _)| *written == path) {
+                    out.push(Diagnostic::error(
+                        "duplicate-path",
+                        value.pos,
+                        format!("duplicate node `{path}` in area `{area}`"),
+                    ));
+                } else if let Some((written, _)) = seen.iter().find(|(_, other)| *other == same) {
+                    out.push(Diagnostic::warning(
+                        "equivalent-path",
+                        value.pos,
+                        format!("node `{path}` is the same folder as `{written}` in area `{area}`"),
+                    ));
+                } else {
+                    seen.push((path, same));
+                }
+            }
+        }
+
+        lint_allow_extra(node, out);
+        if let Some(children) = node.get("children").and_then(Spanned::as_array) {
+            lint_nodes(children, area, out);
+        }
+    }
+}
+
+fn lint_allow_extra(value: &Spanned, out: &mut Vec<Diagnostic>) {
+    let patterns = value.get("allow_extra").and_then(Spanned::as_array);
+    for pattern in patterns.unwrap_or_default() {
+        if let Some(text) = pattern.as_str()
+            && let Err(err) = globset::Glob::new(text)
+        {
+            out.push(Diagnostic::error(
+                "invalid-glob",
+                pattern.pos,
+                format!("`allow_extra` pattern `{text}` is invalid: {}", err.kind()),
+            ));
+        }
+    }
+}
+
+fn lint_rules(rules: &[Spanned], out: &mut Vec<Diagnostic>) {
+    for (index, rule) in rules.iter().enumerate() {
+        if rule.as_object().is_none() {
+            continue;
+        }
+        unknown_keys(rule, RULE_KEYS, "rule", out);
+        let name = rule.get("name").and_then(Spanned::as_str);
+        let label = rule_label(name, index);
+        let mut check = |key: &str, result: Result<(), String>| {
+            if let (Some(value), Err(err)) = (rule.get(key), result) {
+                out.push(Diagnostic::error(
+                    "invalid-rule",
+                    value.pos,
+                    format!("rule {label}: `{key}` {err}"),
+                ));
+            }
+        };
+        let text = |key: &str| rule.get(key).and_then(Spanned::as_str);
+
+        if let Some(action) = text("action") {
+            check(
+                "action",
+                parse_action(action)
+                    .map(drop)
+                    .map_err(|e| format!("is invalid: {e}")),
+            );
+        }
+        if let Some(glob) = text("glob") {
+            check(
+                "glob",
+                globset::Glob::new(glob)
+                    .map(drop)
+                    .map_err(|e| format!("is not a valid glob: {}", e.kind())),
+            );
+        }
+        if let Some(regex) = text("regex") {
+            check(
+                "regex",
+                regex::Regex::new(regex)
+                    .map(drop)
+                    .map_err(|_| "is not a valid regex".to_string()),
+            );
+        }
+        for key in ["larger_than", "smaller_than"] {
+            if let Some(size) = text(key) {
+                check(
+                    key,
+                    parse_size(size)
+                        .map(drop)
+                        .map_err(|e| format!("is invalid: {e}")),
+                );
+            }
+        }
+        for key in ["older_than", "newer_than"] {
+            if let Some(age) = text(key) {
+                check(
+                    key,
+                    parse_duration(age)
+                        .map(drop)
+                        .map_err(|e| format!("is invalid: {e}")),
+                );
+            }
+        }
+    }
+}
+
+fn lint_tidy(tidy: &Spanned, out: &mut Vec<Diagnostic>) {
+    unknown_keys(tidy, TIDY_KEYS, "tidy", out);
+    lint_tidy_values(tidy, "tidy", out);
+    if let Some(desktop) = tidy.get("desktop") {
+        unknown_keys(desktop, DESKTOP_KEYS, "tidy.desktop", out);
+        lint_screenshot_patterns(desktop, out);
+    }
+    if let Some(downloads) = tidy.get("downloads") {
+        unknown_keys(downloads, DOWNLOADS_KEYS, "tidy.downloads", out);
+        lint_tidy_values(downloads, "tidy.downloads", out);
+    }
+    let inboxes = tidy.get("inboxes").and_then(Spanned::as_array);
+    for (index, inbox) in inboxes.unwrap_or_default().iter().enumerate() {
+        if inbox.as_object().is_none() {
+            continue;
+        }
+        let name = inbox.get("name").and_then(Spanned::as_str);
+        let what = match name {
+            Some(name) => format!("tidy.inboxes.{name}"),
+            None => format!("tidy.inboxes[{index}]"),
+        };
+        unknown_keys(inbox, INBOX_KEYS, &what, out);
+        lint_tidy_values(inbox, &what, out);
+        if let Some(rules) = inbox.get("rules").and_then(Spanned::as_array) {
+            lint_rules(rules, out);
+        }
+    }
+}
+
+fn lint_screenshot_patterns(desktop: &Spanned, out: &mut Vec<Diagnostic>) {
+    let mut check = |key: &str, test: fn(&str) -> bool| {
+        let patterns = desktop.get(key).and_then(Spanned::as_array);
+        for pattern in patterns.unwrap_or_default() {
+            if let Some(text) = pattern.as_str()
+                && !test(text)
+            {
+                out.push(Diagnostic::error(
+                    "invalid-tidy",
+                    pattern.pos,
+                    format!("`tidy.desktop.{key}` entry `{text}` is invalid"),
+                ));
+            }
+        }
+    };
+    check("screenshot_globs", |glob| globset::Glob::new(glob).is_ok());
+    check("screenshot_regexes", |regex| {
+        regex::Regex::new(regex).is_ok()
+    });
+}
+
+fn lint_tidy_values(inbox: &Spanned, what: &str, out: &mut Vec<Diagnostic>) {
+    let mut check = |key: &str, result: Result<(), String>| {
+        if let (Some(value), Err(err)) = (inbox.get(key), result) {
+            out.push(Diagnostic::error(
+                "invalid-tidy",
+                value.pos,
+                format!("`{what}.{key}` is invalid: {err}"),
+            ));
+        }
+    };
+    let text = |key: &str| inbox.get(key).and_then(Spanned::as_str);
+    for key in ["older_than", "grace_period"] {
+        if let Some(age) = text(key) {
+            check(key, parse_duration(age).map(drop));
+        }
+    }
+    for key in ["moderate_above", "heavy_above"] {
+        if let Some(size) = text(key) {
+            check(key, parse_size(size).map(drop).map_err(|e| e.to_string()));
+        }
+    }
+}
+
+fn unknown_keys(value: &Spanned, known: &[&str], what: &str, out: &mut Vec<Diagnostic>) {
+    for member in value.as_object().unwrap_or_default() {
+        if !known.contains(&member.key.as_str()) {
+            out.push(Diagnostic::error(
+                "unknown-key",
+                member.key_pos,
+                format!("unknown {what} key `{}`", member.key),
+            ));
+        }
+    }
+}
+
+/// `a`, `a/`, `./a` and `a//` all name the same folder.
+fn same_folder(path: &str) -> String {
+    path.split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn escapes_parent(path: &str) -> bool {
+    let mut depth = 0i32;
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                depth -= 1;
+                if depth < 0 {
+                    return true;
+                }
+            }
+            _ => depth += 1,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Severity, lint_spec};
+    use crate::spec_loader::SpecFormat;
+    use std::path::Path;
+
+    fn lint_json(raw: &str, home: Option<&Path>) -> Vec<super::Diagnostic> {
+        lint_spec(raw, SpecFormat::Json, home)
+    }
+
+    fn codes(raw: &str) -> Vec<&'static str> {
+        lint_json(raw, Some(Path::new("/home/tester")))
+            .into_iter()
+            .map(|d| d.code)
+            .collect()
+    }
+
+    #[test]
+    fn lint_accepts_valid_spec() {
+        let raw = r#"{
+  "version": 1,
+  "areas": [
+    { "name": "System", "root": "~/System", "required": [{ "path": "apps" }] }
+  ]
+}"#;
+        assert!(codes(raw).is_empty());
+    }
+
+    #[test]
+    fn lint_reports_node_path_problems_with_positions() {
+        let raw = r#"{
+  "version": 1,
+  "areas": [
+    {
+      "name": "Docs",
+      "root": "~/Documents",
+      "required": [
+        { "path": "a" },
+        { "path": "a" },
+        { "path": "/abs" },
+        { "path": "x/../../up" },
+        { "path": "" }
+      ]
+    }
+  ]
+}"#;
+        let diagnostics = lint_json(raw, None);
+        let found: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            found,
+            vec![
+                "duplicate-path",
+                "absolute-path",
+                "escaping-path",
+                "empty-path"
+            ]
+        );
+        assert_eq!(diagnostics[0].pos.map(|p| p.line), Some(9));
+    }
+
+    #[test]
+    fn lint_reports_roots_version_names_and_unknown_keys() {
+        let raw = r#"{
+  "version": 7,
+  "aeras": [],
+  "areas": [
+    { "name": "", "root": "~/Documents", "required": [] },
+    { "name": "Docs", "root": "~/Documents", "required": [] },
+    { "name": "School", "root": "~/Documents/school", "required": [{ "path": "x", "childern": [] }] }
+  ]
+}"#;
+        let diagnostics = lint_json(raw, Some(Path::new("/home/tester")));
+        let found: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            found,
+            vec![
+                "unknown-version",
+                "unknown-key",
+                "empty-name",
+                "duplicate-root",
+                "nested-root",
+                "nested-root",
+                "unknown-key"
+            ]
+        );
+        let nested = diagnostics
+            .iter()
+            .find(|d| d.code == "nested-root")
+            .unwrap();
+        assert_eq!(nested.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn lint_reports_parse_errors() {
+        assert_eq!(codes("{ \"version\": 1, "), vec!["parse-error"]);
+    }
+
+    #[test]
+    fn lint_checks_compact_string_nodes() {
+        let raw = r#"{
+  "version": 1,
+  "areas": [
+    { "name": "S", "root": "~/S", "required": ["apps", "life-os/repo", "apps", "/abs"] }
+  ]
+}"#;
+        assert_eq!(codes(raw), vec!["duplicate-path", "absolute-path"]);
+    }
+
+    #[test]
+    fn lint_checks_strict_allow_extra_globs() {
+        let raw = r#"{
+  "version": 1,
+  "areas": [
+    {
+      "name": "S",
+      "root": "~/S",
+      "strict": true,
+      "allow_extra": ["*.localized", "[oops"],
+      "required": [{ "path": "apps", "strict": true, "allow_extra": ["{a,b"] }]
+    }
+  ]
+}"#;
+        assert_eq!(codes(raw), vec!["invalid-glob", "invalid-glob"]);
+    }
+
+    #[test]
+    fn lint_checks_rules() {
+        let raw = r#"{
+  "version": 1,
+  "areas": [],
+  "rules": [
+    { "name": "pdfs", "ext": ["pdf"], "action": "move-to Documents/files" },
+    { "glob": "[oops", "regex": "(", "action": "shred" },
+    { "larger_than": "1 parsec", "older_than": "3y", "action": "keep", "when": "now" }
+  ]
+}"#;
+        assert_eq!(
+            codes(raw),
+            vec![
+                "invalid-rule",
+                "invalid-rule",
+                "invalid-rule",
+                "invalid-rule",
+                "invalid-rule",
+                "unknown-key"
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_checks_tidy_settings() {
+        let raw = r#"{
+  "version": 1,
+  "areas": [],
+  "tidy": {
+    "grace_period": "a moment",
+    "desktop": { "path": "~/Desktop", "busy_screenshots": 20, "busy": 3, "screenshot_regexes": ["^Shot", "("] },
+    "downloads": { "older_than": "2 fortnights", "moderate_above": "2GB", "heavy_above": "lots" },
+    "inboxes": [
+      { "name": "Scans", "path": "~/Scans", "older_than": "30d", "rules": [{ "action": "shred" }] },
+      { "name": "Tmp", "older_than": "soon", "keep": true }
+    ]
+  },
+  "hosts": { "laptop": { "tidy": { "downloads": { "older_than": "3d" } } } }
+}"#;
+        assert_eq!(
+            codes(raw),
+            vec![
+                "invalid-tidy",
+                "unknown-key",
+                "invalid-tidy",
+                "invalid-tidy",
+                "invalid-tidy",
+                "invalid-rule",
+                "invalid-tidy",
+                "unknown-key"
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_checks_host_overlays() {
+        let raw = r#"{
+  "version": 1,
+  "include": ["common.json"],
+  "hosts": {
+    "music": { "areas": [{ "name": "Documents", "required": ["audio", "audio"] }], "extra": 1 }
+  },
+  "areas": []
+}"#;
+        assert_eq!(codes(raw), vec!["duplicate-path", "unknown-key"]);
+    }
+
+    #[test]
+    fn lint_checks_toml_structure_without_positions() {
+        let raw = r#"
+version = 1
+
+[[areas]]
+name = "Docs"
+root = "~/Documents"
+required = [{ path = "a" }, { path = "a" }]
+"#;
+        let diagnostics = lint_spec(raw, SpecFormat::Toml, None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "duplicate-path");
+        assert!(diagnostics[0].pos.is_none());
+
+        let raw = r#"
+version = 1
+
+[[areas]]
+name = "Docs"
+root = "~/Documents"
+required = ["a", "a/", "./a", "b/./c", "b/c//", "ab"]
+"#;
+        let diagnostics = lint_spec(raw, SpecFormat::Toml, None);
+        let found: Vec<_> = diagnostics.iter().map(|d| (d.code, d.severity)).collect();
+        assert_eq!(found, vec![("equivalent-path", Severity::Warning); 3]);
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("`a/` is the same folder as `a`")
+        );
+
+        let broken = lint_spec("version = \n", SpecFormat::Toml, None);
+        assert_eq!(broken[0].code, "parse-error");
+        assert_eq!(broken[0].pos.map(|p| p.line), Some(1));
+    }
+}
@@ -1,8 +1,21 @@
 mod check;
 mod cli;
 mod commands;
+mod disk_usage;
+mod duration;
+mod hash;
+mod journal;
+mod json_spans;
+mod lint;
+mod output;
+mod rules;
+mod screenshots;
 mod spec;
 mod spec_loader;
+mod transfer;
+mod trash;
+mod user_dirs;
+mod vars;
 
 use anyhow::Result;
 
@@ -0,0 +1,90 @@
+//! `--format json` output: every report is wrapped in a versioned envelope.
+//! `life-os schema` prints the JSON Schema generated from these types; the
+//! committed copy lives in `schema/report.schema.json`.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::commands::doctor::DoctorReport;
+use crate::commands::dupes::DupesReport;
+use crate::commands::init::InitReport;
+use crate::commands::tidy::TidyReport;
+
+/// Bumped when a field is renamed, removed or changes type. Adding fields
+/// does not bump it.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(title = "life-os report")]
+pub struct Envelope<'a> {
+    pub schema_version: u32,
+    /// The process exit code of this run.
+    pub exit_code: u8,
+    #[serde(flatten)]
+    pub report: Report<'a>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "command", content = "report", rename_all = "snake_case")]
+pub enum Report<'a> {
+    Doctor(&'a DoctorReport),
+    Init(&'a InitReport),
+    Tidy(&'a TidyReport),
+    Dupes(&'a DupesReport),
+}
+
+pub fn print_json(report: Report<'_>, exit_code: u8) -> Result<()> {
+    let envelope = Envelope {
+        schema_version: SCHEMA_VERSION,
+        exit_code,
+        report,
+    };
+    println!("{}", serde_json::to_string_pretty(&envelope)?);
+    Ok(())
+}
+
+pub fn schema() -> Result<String> {
+    let schema = schemars::schema_for!(Envelope<'static>);
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Envelope, Report, SCHEMA_VERSION, schema};
+    use crate::commands::tidy::TidyReport;
+
+    #[test]
+    fn committed_schema_matches_generated_schema() {
+        let committed = include_str!("../schema/report.schema.json");
+        assert_eq!(
+            committed.trim_end(),
+            schema().unwrap(),
+            "run `life-os schema > schema/report.schema.json`"
+        );
+    }
+
+    #[test]
+    fn envelope_carries_version_command_and_exit_code() {
+        let report = TidyReport::default();
+        let envelope = Envelope {
+            schema_version: SCHEMA_VERSION,
+            exit_code: 0,
+            report: Report::Tidy(&report),
+        };
+        let value = serde_json::to_value(&envelope).unwrap();
+
+        assert_eq!(value["schema_version"], 2);
+        assert_eq!(value["command"], "tidy");
+        assert_eq!(value["exit_code"], 0);
+        assert_eq!(value["report"]["applied"], false);
+        assert!(value["report"]["inboxes"].is_array());
+    }
+}
@@ -0,0 +1,297 @@
+//! Inbox routing: rule lists from the spec are compiled once and tried in
+//! order for every item; the first match decides what `tidy` does with it.
+
+use anyhow::{Context, Result, anyhow, bail};
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::duration::parse_duration;
+use crate::spec::{self, SpecFile, rule_label};
+use crate::spec_loader::expand_root;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    MoveTo,
+    Quarantine,
+    /// Removed according to `--delete-mode`.
+    Delete,
+    Keep,
+}
+
+impl RuleAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuleAction::MoveTo => "move-to",
+            RuleAction::Quarantine => "quarantine",
+            RuleAction::Delete => "delete",
+            RuleAction::Keep => "keep",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// `` `name` `` or `#n`, for reports.
+    pub label: String,
+    pub action: RuleAction,
+    /// Target folder for `move-to`.
+    pub destination: Option<PathBuf>,
+    /// Move into `YYYY/MM` below `destination` (screenshots).
+    pub date_buckets: bool,
+    ext: Vec<String>,
+    glob: Option<GlobMatcher>,
+    regex: Option<Regex>,
+    larger_than: Option<u64>,
+    smaller_than: Option<u64>,
+    older_than: Option<Duration>,
+    newer_than: Option<Duration>,
+}
+
+/// What a rule gets to look at.
+pub struct Item<'a> {
+    pub name: &'a str,
+    pub size: u64,
+    /// Time since last modification.
+    pub age: Duration,
+}
+
+impl Rule {
+    /// A `move-to` rule for names matching `glob` and `regex`, for built-in
+    /// inboxes.
+    pub fn move_matching(
+        name: &str,
+        glob: Option<&str>,
+        regex: Option<&str>,
+        destination: PathBuf,
+    ) -> Result<Self> {
+        Ok(Rule {
+            label: rule_label(Some(name), 0),
+            action: RuleAction::MoveTo,
+            destination: Some(destination),
+            date_buckets: false,
+            ext: Vec::new(),
+            glob: glob
+                .map(|g| Glob::new(g).map(|g| g.compile_matcher()))
+                .transpose()?,
+            regex: regex.map(Regex::new).transpose()?,
+            larger_than: None,
+            smaller_than: None,
+            older_than: None,
+            newer_than: None,
+        })
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        let ext = item
+            .name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default();
+        (self.ext.is_empty() || self.ext.contains(&ext))
+            && self.glob.as_ref().is_none_or(|g| g.is_match(item.name))
+            && self.regex.as_ref().is_none_or(|r| r.is_match(item.name))
+            && self.larger_than.is_none_or(|min| item.size > min)
+            && self.smaller_than.is_none_or(|max| item.size < max)
+            && self.older_than.is_none_or(|min| item.age > min)
+            && self.newer_than.is_none_or(|max| item.age < max)
+    }
+}
+
+/// Compiles `raw` (the spec's `rules` or an inbox's), resolving `move-to`
+/// targets against the spec's area roots.
+pub fn compile(raw: &[spec::Rule], spec: &SpecFile, home: &Path) -> Result<Vec<Rule>> {
+    raw.iter()
+        .enumerate()
+        .map(|(index, raw)| {
+            let label = rule_label(raw.name.as_deref(), index);
+            compile_rule(raw, label.clone(), spec, home).with_context(|| format!("in rule {label}"))
+        })
+        .collect()
+}
+
+fn compile_rule(raw: &spec::Rule, label: String, spec: &SpecFile, home: &Path) -> Result<Rule> {
+    let (action, target) = parse_action(&raw.action)?;
+    let destination = match target {
+        Some(target) => Some(resolve_target(target, spec, home)?),
+        None => None,
+    };
+    Ok(Rule {
+        label,
+        action,
+        destination,
+        date_buckets: false,
+        ext: raw
+            .ext
+            .iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .collect(),
+        glob: raw
+            .glob
+            .as_deref()
+            .map(|g| Glob::new(g).map(|g| g.compile_matcher()))
+            .transpose()?,
+        regex: raw.regex.as_deref().map(Regex::new).transpose()?,
+        larger_than: raw.larger_than.as_deref().map(parse_size).transpose()?,
+        smaller_than: raw.smaller_than.as_deref().map(parse_size).transpose()?,
+        older_than: raw.older_than.as_deref().map(age).transpose()?,
+        newer_than: raw.newer_than.as_deref().map(age).transpose()?,
+    })
+}
+
+fn age(input: &str) -> Result<Duration> {
+    parse_duration(input).map_err(|err| anyhow!(err))
+}
+
+/// Splits `move-to Documents/finance` into the action and its target.
+pub fn parse_action(action: &str) -> Result<(RuleAction, Option<&str>)> {
+    let action = action.trim();
+    let (verb, target) = match action.split_once(char::is_whitespace) {
+        Some((verb, target)) => (verb, Some(target.trim())),
+        None => (action, None),
+    };
+    Ok(match (verb, target) {
+        ("move-to", Some(target)) if !target.is_empty() => (RuleAction::MoveTo, Some(target)),
+        ("move-to", _) => bail!("`move-to` needs a target like `Documents/files`"),
+        ("quarantine", None) => (RuleAction::Quarantine, None),
+        ("delete", None) => (RuleAction::Delete, None),
+        ("keep", None) => (RuleAction::Keep, None),
+        _ => bail!(
+            "unknown action `{action}` (expected `move-to <area>/<path>`, `quarantine`, `delete` or `keep`)"
+        ),
+    })
+}
+
+/// `Documents/finance` is the `finance` folder under the `Documents` area.
+/// Area names are case-insensitive; `..` may not leave the area.
+pub fn resolve_target(target: &str, spec: &SpecFile, home: &Path) -> Result<PathBuf> {
+    let (area_name, rest) = target.split_once('/').unwrap_or((target, ""));
+    let area = spec
+        .areas
+        .iter()
+        .find(|area| area.name.eq_ignore_ascii_case(area_name))
+        .with_context(|| format!("unknown area `{area_name}` in `move-to {target}`"))?;
+    let rest = Path::new(rest.trim_matches('/'));
+    if rest.components().any(|c| c == Component::ParentDir) {
+        bail!("`..` is not allowed in `move-to {target}`");
+    }
+    Ok(expand_root(&area.root, home).join(rest))
+}
+
+/// Parses `512`, `10KB`, `1.5 GB` (1024-based, like the tidy report).
+pub fn parse_size(input: &str) -> Result<u64> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("invalid size `{input}` (expected e.g. 500KB, 1GB)"))?;
+    let scale: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => bail!("unknown size unit `{unit}` in `{input}` (use B, KB, MB, GB or TB)"),
+    };
+    Ok((value * scale as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Item, RuleAction, compile, parse_size, resolve_target};
+    use crate::spec::SpecFile;
+    use std::path::Path;
+    use std::time::Duration;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn spec(raw: &str) -> SpecFile {
+        serde_json::from_str(raw).expect("spec")
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let spec = spec(
+            r#"{
+  "version": 1,
+  "areas": [{ "name": "Documents", "root": "~/Documents" }],
+  "rules": [
+    { "name": "invoices", "ext": ["PDF"], "regex": "(?i)invoice", "action": "move-to Documents/finance" },
+    { "ext": ["pdf"], "action": "move-to Documents/files" },
+    { "glob": "*.iso", "larger_than": "1GB", "action": "delete" },
+    { "older_than": "30d", "action": "quarantine" },
+    { "action": "keep" }
+  ]
+}"#,
+        );
+        let rules = compile(&spec.rules, &spec, Path::new("/home/u")).expect("compile");
+        let route = |name: &str, size: u64, age: Duration| {
+            let item = Item { name, size, age };
+            let rule = rules.iter().find(|r| r.matches(&item)).unwrap();
+            (rule.label.clone(), rule.action, rule.destination.clone())
+        };
+
+        let (label, action, dest) = route("Invoice-2026.pdf", 10, DAY);
+        assert_eq!(label, "`invoices`");
+        assert_eq!(action, RuleAction::MoveTo);
+        assert_eq!(dest.unwrap(), Path::new("/home/u/Documents/finance"));
+        assert_eq!(route("paper.PDF", 10, DAY).0, "#2");
+        assert_eq!(route("big.iso", 2 << 30, DAY).1, RuleAction::Delete);
+        assert_eq!(route("small.iso", 10, 40 * DAY).1, RuleAction::Quarantine);
+        assert_eq!(route("small.iso", 10, DAY).1, RuleAction::Keep);
+    }
+
+    #[test]
+    fn compile_rejects_bad_rules() {
+        let err = |rule: &str| {
+            let raw = format!(
+                r#"{{ "version": 1, "areas": [{{ "name": "Documents", "root": "~/D" }}], "rules": [{rule}] }}"#
+            );
+            let spec = spec(&raw);
+            let error = compile(&spec.rules, &spec, Path::new("/home/u")).unwrap_err();
+            format!("{error:#}")
+        };
+
+        assert!(err(r#"{ "action": "move-to Music/x" }"#).contains("unknown area `Music`"));
+        assert!(
+            err(r#"{ "action": "move-to Documents/../../etc" }"#).contains("`..` is not allowed")
+        );
+        assert!(err(r#"{ "action": "move-to Documents/a/../b" }"#).contains("`..`"));
+        assert!(err(r#"{ "name": "r", "action": "shred" }"#).starts_with("in rule `r`"));
+        assert!(err(r#"{ "regex": "(", "action": "keep" }"#).starts_with("in rule #1"));
+        assert!(err(r#"{ "larger_than": "3 parsecs", "action": "keep" }"#).contains("size unit"));
+    }
+
+    #[test]
+    fn move_to_matches_areas_case_insensitively() {
+        let spec =
+            spec(r#"{ "version": 1, "areas": [{ "name": "Documents", "root": "~/Documents" }] }"#);
+        let home = Path::new("/home/u");
+        for target in [
+            "documents/finance",
+            "DOCUMENTS/finance/",
+            "Documents/./finance",
+        ] {
+            assert_eq!(
+                resolve_target(target, &spec, home).unwrap(),
+                Path::new("/home/u/Documents/finance"),
+                "{target}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_size_accepts_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10KB").unwrap(), 10 * 1024);
+        assert_eq!(parse_size("1.5 GB").unwrap(), 3 << 29);
+        assert!(parse_size("GB").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Screenshot file names. The built-in catalogue covers the default names of
+//! the common tools; the spec can add globs and regexes of its own
+//! (`tidy.desktop.screenshot_globs` / `screenshot_regexes`). Screenshots are
+//! filed into `YYYY/MM` folders by the capture date in their name, or by
+//! modification time when the name has none.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::rules::Rule;
+
+/// Default names per tool, as regexes on the file name. Flameshot's default
+/// (`2026-10-16_10-00.png`) is left out: it is just a date and time, like
+/// photos from many cameras. Add it with `screenshot_regexes` if wanted.
+pub const CATALOGUE: &[(&str, &str)] = &[
+    // `Screenshot 2026-10-16 at 10.00.00.png`, before Mojave `Screen Shot ...`.
+    ("macOS", r"^Screen ?[Ss]hot .+\.(png|jpe?g|heic|tiff?)$"),
+    // `Screenshot from 2026-10-16 10-00-00.png`
+    (
+        "GNOME",
+        r"^Screenshot from \d{4}-\d{2}-\d{2} \d{2}-\d{2}-\d{2}(-\d+)?\.(png|jpe?g)$",
+    ),
+    // `Screenshot_20261016_100000.png`
+    (
+        "KDE Spectacle",
+        r"^Screenshot_\d{8}_\d{6}(-\d+)?\.(png|jpe?g|webp)$",
+    ),
+    // `Screenshot_2026-10-16_10-00-00.png`
+    (
+        "Xfce",
+        r"^Screenshot_\d{4}-\d{2}-\d{2}_\d{2}-\d{2}-\d{2}\.(png|jpe?g)$",
+    ),
+    // `2026-10-16-100000_1920x1080_scrot.png`
+    (
+        "scrot",
+        r"^\d{4}-\d{2}-\d{2}-\d{6}_\d+x\d+_scrot\.(png|jpe?g)$",
+    ),
+    // `Screenshot (12).png`, `Screenshot 2026-10-16 100000.png`
+    (
+        "Windows",
+        r"^Screenshot( \(\d+\)| \d{4}-\d{2}-\d{2} \d{6})\.(png|jpe?g)$",
+    ),
+];
+
+/// One `move-to` rule per pattern, all labelled `screenshots`.
+pub fn rules(destination: &Path, globs: &[String], regexes: &[String]) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for (_, regex) in CATALOGUE {
+        rules.push(rule(None, Some(regex), destination)?);
+    }
+    for glob in globs {
+        rules.push(
+            rule(Some(glob), None, destination)
+                .with_context(|| format!("in screenshot glob `{glob}`"))?,
+        );
+    }
+    for regex in regexes {
+        rules.push(
+            rule(None, Some(regex), destination)
+                .with_context(|| format!("in screenshot regex `{regex}`"))?,
+        );
+    }
+    Ok(rules)
+}
+
+fn rule(glob: Option<&str>, regex: Option<&str>, destination: &Path) -> Result<Rule> {
+    let mut rule = Rule::move_matching("screenshots", glob, regex, destination.to_path_buf())?;
+    rule.date_buckets = true;
+    Ok(rule)
+}
+
+/// `YYYY-MM-DD` or `YYYYMMDD`, not part of a longer number.
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|\D)((?:19|20)\d{2})-?(\d{2})-?(\d{2})(?:\D|$)").expect("valid regex")
+});
+
+/// The capture date in a screenshot's file name, if it has one.
+pub fn capture_date(name: &str) -> Option<NaiveDate> {
+    DATE.captures_iter(name).find_map(|caps| {
+        let number = |i: usize| caps[i].parse::<u32>().ok();
+        NaiveDate::from_ymd_opt(number(1)? as i32, number(2)?, number(3)?)
+    })
+}
+
+/// `YYYY/MM` for the screenshot at `path`.
+pub fn bucket(path: &Path) -> PathBuf {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let date = capture_date(name).unwrap_or_else(|| {
+        let modified = fs::symlink_metadata(path).and_then(|meta| meta.modified());
+        modified
+            .map(DateTime::<Local>::from)
+            .unwrap_or_else(|_| Local::now())
+            .date_naive()
+    });
+    PathBuf::from(format!("{:04}/{:02}", date.year(), date.month()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bucket, capture_date, rules};
+    use crate::rules::Item;
+    use chrono::NaiveDate;
+    use filetime::{FileTime, set_file_mtime};
+    use std::path::Path;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn catalogue_and_spec_patterns_recognise_screenshots() {
+        let rules = rules(
+            Path::new("/shots"),
+            &["Bildschirmfoto *.png".to_string()],
+            &[r"^capture-\d+\.webp$".to_string()],
+        )
+        .expect("rules");
+        let is_screenshot = |name: &str| {
+            let item = Item {
+                name,
+                size: 1,
+                age: Duration::ZERO,
+            };
+            rules.iter().any(|rule| rule.matches(&item))
+        };
+
+        for name in [
+            "Screenshot 2026-10-16 at 10.00.00.png",
+            "Screen Shot 2019-05-01 at 9.41.07 AM.png",
+            "Screenshot 2026-10-16 at 10.00.00.jpg",
+            "Screenshot from 2026-10-16 10-00-00.png",
+            "Screenshot_20261016_100000.png",
+            "Screenshot_20261016_100000.webp",
+            "Screenshot_2026-10-16_10-00-00.png",
+            "2026-10-16-100000_1920x1080_scrot.png",
+            "Screenshot (12).png",
+            "Bildschirmfoto 2026-10-16 um 10.00.00.png",
+            "capture-42.webp",
+        ] {
+            assert!(is_screenshot(name), "{name}");
+        }
+        for name in [
+            "report.pdf",
+            "Screenshot.txt",
+            "holiday.png",
+            "2026-10-16 notes.png",
+            "capture-42.png",
+            // Date-named photos, and Flameshot's look-alike default.
+            "2026-10-16_10-00.jpg",
+            "2026-10-16_10-00-00.png",
+            "20261016_100000.jpg",
+        ] {
+            assert!(!is_screenshot(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn capture_date_reads_common_name_formats() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(
+            capture_date("Screenshot 2026-10-16 at 10.00.00.png"),
+            date(2026, 10, 16)
+        );
+        assert_eq!(
+            capture_date("Screenshot_20261016_100000.png"),
+            date(2026, 10, 16)
+        );
+        assert_eq!(
+            capture_date("2025-01-02-100000_1920x1080_scrot.png"),
+            date(2025, 1, 2)
+        );
+        assert_eq!(capture_date("Screenshot (12).png"), None);
+        assert_eq!(capture_date("Screenshot 2026-02-31.png"), None);
+        assert_eq!(capture_date("IMG_120261016.png"), None);
+    }
+
+    #[test]
+    fn bucket_falls_back_to_modification_time() {
+        let dir = tempdir().expect("tempdir");
+        let named = dir.path().join("Screenshot from 2024-03-05 10-00-00.png");
+        let unnamed = dir.path().join("Screenshot (3).png");
+        for path in [&named, &unnamed] {
+            std::fs::write(path, "x").expect("write");
+        }
+        // 2023-07-15 12:00 UTC
+        set_file_mtime(&unnamed, FileTime::from_unix_time(1_689_422_400, 0)).expect("mtime");
+
+        assert_eq!(bucket(&named), Path::new("2024/03"));
+        assert_eq!(bucket(&unnamed), Path::new("2023/07"));
+    }
+
+    #[test]
+    fn rules_reject_bad_spec_patterns() {
+        let err = rules(Path::new("/s"), &["[oops".to_string()], &[]).unwrap_err();
+        assert!(format!("{err:#}").starts_with("in screenshot glob `[oops`"));
+        let err = rules(Path::new("/s"), &[], &["(".to_string()]).unwrap_err();
+        assert!(format!("{err:#}").starts_with("in screenshot regex `(`"));
+    }
+}
@@ -1,22 +1,628 @@
-use serde::Deserialize;
+use anyhow::{Context, Result, bail};
+use globset::Glob;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, de::value::MapAccessDeserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::vars::Vars;
+
+pub const CURRENT_VERSION: u32 = 1;
 
 #[derive(Debug, Deserialize)]
 pub struct SpecFile {
-    #[allow(dead_code)]
     pub version: u32,
+    /// Other spec files merged into this one, relative to this file.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Per-hostname additions, merged only on the matching machine.
+    #[serde(default)]
+    pub hosts: BTreeMap<String, HostOverlay>,
+    /// User-defined `${name}` values for roots and node paths.
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub areas: Vec<Area>,
+    /// Downloads routing rules, tried in order by `tidy`.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub tidy: TidySettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HostOverlay {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default)]
     pub areas: Vec<Area>,
+    #[serde(default)]
+    pub tidy: TidySettings,
+}
+
+/// Where and how `tidy` cleans up. Unset values keep the built-in
+/// defaults (see `tidy::TidyOptions::new`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TidySettings {
+    #[serde(default)]
+    pub desktop: DesktopSettings,
+    #[serde(default)]
+    pub downloads: DownloadsSettings,
+    /// Items modified more recently are left alone; defaults to `5m`.
+    pub grace_period: Option<String>,
+    /// Sizes leave out folders on other filesystems (mount points).
+    pub one_filesystem: Option<bool>,
+    /// Further inbox folders, tidied after Desktop and Downloads.
+    #[serde(default)]
+    pub inboxes: Vec<InboxSettings>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DesktopSettings {
+    /// Defaults to `{desktop}` (`XDG_DESKTOP_DIR`).
+    pub path: Option<String>,
+    /// Fail instead of reporting the folder as absent.
+    pub required: Option<bool>,
+    /// Where screenshots go; defaults to `{documents}/screenshots`.
+    pub screenshots: Option<String>,
+    /// Screenshot names on top of the built-in catalogue.
+    #[serde(default)]
+    pub screenshot_globs: Vec<String>,
+    #[serde(default)]
+    pub screenshot_regexes: Vec<String>,
+    /// The Desktop is busy above this many screenshots...
+    pub busy_screenshots: Option<usize>,
+    /// ...or this many other items.
+    pub busy_other: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DownloadsSettings {
+    /// Defaults to `{downloads}` (`XDG_DOWNLOAD_DIR`).
+    pub path: Option<String>,
+    /// Fail instead of reporting the folder as absent.
+    pub required: Option<bool>,
+    /// Age after which unmatched items are deleted, like `7d`.
+    pub older_than: Option<String>,
+    /// Size above which Downloads counts as moderate, like `1GB`.
+    pub moderate_above: Option<String>,
+    /// Size above which it counts as heavy.
+    pub heavy_above: Option<String>,
+    /// More items than this bumps the level by one.
+    pub many_items: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InboxSettings {
+    pub name: String,
+    pub path: Option<String>,
+    /// Fail instead of reporting the folder as absent.
+    pub required: Option<bool>,
+    /// Tried in order, like the top-level Downloads `rules`.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Unmatched items older than this leave the inbox; unset keeps them.
+    pub older_than: Option<String>,
+    /// `<area>/<path>` for leaving items; unset deletes them.
+    pub destination: Option<String>,
+    pub moderate_above: Option<String>,
+    pub heavy_above: Option<String>,
+    pub many_items: Option<usize>,
+}
+
+fn set<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+impl TidySettings {
+    /// Applies every value `other` sets on top of `self`. Inboxes merge by
+    /// `name` (case-insensitive, like `tidy --inbox`); their rules are appended.
+    pub fn overlay(&mut self, other: TidySettings) {
+        for inbox in other.inboxes {
+            let Some(existing) = self
+                .inboxes
+                .iter_mut()
+                .find(|i| i.name.eq_ignore_ascii_case(&inbox.name))
+            else {
+                self.inboxes.push(inbox);
+                continue;
+            };
+            set(&mut existing.path, inbox.path);
+            set(&mut existing.required, inbox.required);
+            existing.rules.extend(inbox.rules);
+            set(&mut existing.older_than, inbox.older_than);
+            set(&mut existing.destination, inbox.destination);
+            set(&mut existing.moderate_above, inbox.moderate_above);
+            set(&mut existing.heavy_above, inbox.heavy_above);
+            set(&mut existing.many_items, inbox.many_items);
+        }
+        set(&mut self.grace_period, other.grace_period);
+        set(&mut self.one_filesystem, other.one_filesystem);
+        let (desktop, downloads) = (other.desktop, other.downloads);
+        set(&mut self.desktop.path, desktop.path);
+        set(&mut self.desktop.required, desktop.required);
+        set(&mut self.desktop.screenshots, desktop.screenshots);
+        self.desktop
+            .screenshot_globs
+            .extend(desktop.screenshot_globs);
+        self.desktop
+            .screenshot_regexes
+            .extend(desktop.screenshot_regexes);
+        set(&mut self.desktop.busy_screenshots, desktop.busy_screenshots);
+        set(&mut self.desktop.busy_other, desktop.busy_other);
+        set(&mut self.downloads.path, downloads.path);
+        set(&mut self.downloads.required, downloads.required);
+        set(&mut self.downloads.older_than, downloads.older_than);
+        set(&mut self.downloads.moderate_above, downloads.moderate_above);
+        set(&mut self.downloads.heavy_above, downloads.heavy_above);
+        set(&mut self.downloads.many_items, downloads.many_items);
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Area {
     pub name: String,
+    #[serde(default)]
     pub root: String, // "~/System"
+    #[serde(default)]
     pub required: Vec<Node>,
+    /// Report immediate children of `root` that are not declared.
+    #[serde(default)]
+    pub strict: bool,
+    /// Globs for undeclared children that `strict` should tolerate.
+    #[serde(default)]
+    pub allow_extra: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A Downloads routing rule. Every condition that is set must hold; a rule
+/// without conditions matches everything. See `rules::compile`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Extensions without the dot, case-insensitive.
+    #[serde(default)]
+    pub ext: Vec<String>,
+    /// Glob on the file name.
+    #[serde(default)]
+    pub glob: Option<String>,
+    /// Regex on the file name.
+    #[serde(default)]
+    pub regex: Option<String>,
+    /// Sizes like `10MB`.
+    #[serde(default)]
+    pub larger_than: Option<String>,
+    #[serde(default)]
+    pub smaller_than: Option<String>,
+    /// Ages like `30d`, by modification time.
+    #[serde(default)]
+    pub older_than: Option<String>,
+    #[serde(default)]
+    pub newer_than: Option<String>,
+    /// `move-to <area>/<path>`, `quarantine`, `delete` or `keep`.
+    pub action: String,
+}
+
+/// A required folder. Deserializes from `{ "path": "x", "children": [...] }`
+/// or from a plain string (`"apps"`, `"life-os/repo"`).
+#[derive(Debug, Default)]
 pub struct Node {
     pub path: String,
-    #[serde(default)]
     pub children: Vec<Node>,
+    pub strict: bool,
+    pub allow_extra: Vec<String>,
+    pub symlink: SymlinkPolicy,
+    /// Spec file that first declared this folder.
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct NodeFields {
+    path: String,
+    #[serde(default)]
+    children: Vec<Node>,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    allow_extra: Vec<String>,
+    #[serde(default)]
+    symlink: SymlinkPolicy,
+}
+
+/// Whether a required folder may be a symlink to a directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a path string or a node object")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Node, E> {
+                Ok(Node {
+                    path: value.to_string(),
+                    ..Node::default()
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Node, A::Error> {
+                let fields = NodeFields::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(Node {
+                    path: fields.path,
+                    children: fields.children,
+                    strict: fields.strict,
+                    allow_extra: fields.allow_extra,
+                    symlink: fields.symlink,
+                    ..Node::default()
+                })
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+impl SpecFile {
+    /// Expands slash paths into nested nodes and merges siblings that name
+    /// the same folder, so every spelling of a tree compares equal.
+    pub fn normalize(&mut self) {
+        let host_areas = self.hosts.values_mut().flat_map(|h| h.areas.iter_mut());
+        for area in self.areas.iter_mut().chain(host_areas) {
+            area.required = normalize_nodes(std::mem::take(&mut area.required));
+        }
+    }
+
+    /// Records `path` as the origin of every node and resolves `include`
+    /// entries against the directory `path` lives in.
+    pub fn set_source(&mut self, path: &Path) {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let resolve = |include: &mut String| {
+            if !include.starts_with('/') && !include.starts_with("~/") {
+                *include = dir.join(&*include).to_string_lossy().into_owned();
+            }
+        };
+        self.include.iter_mut().for_each(resolve);
+        for host in self.hosts.values_mut() {
+            host.include.iter_mut().for_each(resolve);
+        }
+
+        let host_areas = self.hosts.values_mut().flat_map(|h| h.areas.iter_mut());
+        for area in self.areas.iter_mut().chain(host_areas) {
+            set_node_source(&mut area.required, path);
+        }
+    }
+
+    /// Merges `other` into `self`: areas by `name`, nodes by `path`.
+    pub fn merge(&mut self, other: SpecFile) -> Result<()> {
+        self.include.extend(other.include);
+        self.vars.extend(other.vars);
+        // Rules from the including file come first, so they win.
+        self.rules.extend(other.rules);
+        self.tidy.overlay(other.tidy);
+        for (name, host) in other.hosts {
+            let entry = self.hosts.entry(name).or_default();
+            entry.include.extend(host.include);
+            entry.vars.extend(host.vars);
+            entry.areas.extend(host.areas);
+            entry.tidy.overlay(host.tidy);
+        }
+        self.merge_areas(other.areas)
+    }
+
+    pub fn merge_areas(&mut self, areas: Vec<Area>) -> Result<()> {
+        for area in areas {
+            let Some(existing) = self.areas.iter_mut().find(|a| a.name == area.name) else {
+                self.areas.push(area);
+                continue;
+            };
+            if existing.root.is_empty() {
+                existing.root = area.root;
+            } else if !area.root.is_empty() && area.root != existing.root {
+                bail!(
+                    "area `{}` has conflicting roots: `{}` and `{}`",
+                    area.name,
+                    existing.root,
+                    area.root
+                );
+            }
+            existing.strict |= area.strict;
+            existing.allow_extra.extend(area.allow_extra);
+            for node in area.required {
+                merge_node(&mut existing.required, node);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every `allow_extra` entry is a valid glob.
+pub fn validate_globs(spec: &SpecFile) -> Result<()> {
+    fn check(patterns: &[String], what: &str) -> Result<()> {
+        for pattern in patterns {
+            Glob::new(pattern)
+                .with_context(|| format!("invalid allow_extra glob `{pattern}` in {what}"))?;
+        }
+        Ok(())
+    }
+    fn check_nodes(nodes: &[Node], area: &str, parent: &str) -> Result<()> {
+        for node in nodes {
+            let display = format!("{parent}{}", node.path);
+            check(
+                &node.allow_extra,
+                &format!("area `{area}`, node `{display}`"),
+            )?;
+            check_nodes(&node.children, area, &format!("{display}/"))?;
+        }
+        Ok(())
+    }
+
+    for area in &spec.areas {
+        check(&area.allow_extra, &format!("area `{}`", area.name))?;
+        check_nodes(&area.required, &area.name, "")?;
+    }
+    Ok(())
+}
+
+/// Expands `${...}` and `{...}` references in area roots and node paths.
+pub fn expand_vars(spec: &mut SpecFile, vars: &Vars) -> Result<()> {
+    for area in &mut spec.areas {
+        area.root = vars
+            .expand(&area.root)
+            .with_context(|| format!("in area `{}` root", area.name))?;
+        expand_nodes(&mut area.required, vars, &area.name, "")?;
+    }
+    for (index, rule) in spec.rules.iter_mut().enumerate() {
+        rule.action = vars
+            .expand(&rule.action)
+            .with_context(|| format!("in rule {}", rule_label(rule.name.as_deref(), index)))?;
+    }
+    let tidy_paths = [
+        (&mut spec.tidy.desktop.path, "tidy.desktop.path"),
+        (
+            &mut spec.tidy.desktop.screenshots,
+            "tidy.desktop.screenshots",
+        ),
+        (&mut spec.tidy.downloads.path, "tidy.downloads.path"),
+    ];
+    for (path, key) in tidy_paths {
+        if let Some(path) = path {
+            *path = vars.expand(path).with_context(|| format!("in `{key}`"))?;
+        }
+    }
+    for inbox in &mut spec.tidy.inboxes {
+        let context = || format!("in inbox `{}`", inbox.name);
+        for value in [&mut inbox.path, &mut inbox.destination]
+            .into_iter()
+            .flatten()
+        {
+            *value = vars.expand(value).with_context(context)?;
+        }
+        for (index, rule) in inbox.rules.iter_mut().enumerate() {
+            rule.action = vars.expand(&rule.action).with_context(|| {
+                format!(
+                    "in inbox `{}`, rule {}",
+                    inbox.name,
+                    rule_label(rule.name.as_deref(), index)
+                )
+            })?;
+        }
+    }
+    spec.normalize();
+    Ok(())
+}
+
+fn expand_nodes(nodes: &mut [Node], vars: &Vars, area: &str, parent: &str) -> Result<()> {
+    for node in nodes {
+        let display = format!("{parent}{}", node.path);
+        node.path = vars
+            .expand(&node.path)
+            .with_context(|| format!("in area `{area}`, node `{display}`"))?;
+        expand_nodes(&mut node.children, vars, area, &format!("{display}/"))?;
+    }
+    Ok(())
+}
+
+/// `` `name` `` for named rules, `#3` (1-based) otherwise.
+pub fn rule_label(name: Option<&str>, index: usize) -> String {
+    match name {
+        Some(name) => format!("`{name}`"),
+        None => format!("#{}", index + 1),
+    }
+}
+
+fn set_node_source(nodes: &mut [Node], path: &Path) {
+    for node in nodes {
+        node.source = Some(path.to_path_buf());
+        set_node_source(&mut node.children, path);
+    }
+}
+
+pub fn normalize_nodes(nodes: Vec<Node>) -> Vec<Node> {
+    let mut out: Vec<Node> = Vec::new();
+    for node in nodes {
+        merge_node(&mut out, expand_node(node));
+    }
+    out
+}
+
+fn expand_node(mut node: Node) -> Node {
+    let mut parts: Vec<String> = node
+        .path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .map(str::to_string)
+        .collect();
+    node.children = normalize_nodes(std::mem::take(&mut node.children));
+    if let Some(leaf) = parts.pop() {
+        node.path = leaf;
+    }
+
+    // Implied parents only carry the source; settings stay on the leaf.
+    let mut current = node;
+    while let Some(parent) = parts.pop() {
+        let source = current.source.clone();
+        current = Node {
+            path: parent,
+            children: vec![current],
+            source,
+            ..Node::default()
+        };
+    }
+    current
+}
+
+fn merge_node(siblings: &mut Vec<Node>, node: Node) {
+    match siblings.iter_mut().find(|s| s.path == node.path) {
+        Some(existing) => {
+            existing.strict |= node.strict;
+            existing.allow_extra.extend(node.allow_extra);
+            if node.symlink == SymlinkPolicy::Deny {
+                existing.symlink = SymlinkPolicy::Deny;
+            }
+            for child in node.children {
+                merge_node(&mut existing.children, child);
+            }
+        }
+        None => siblings.push(node),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Node, SpecFile, expand_vars};
+    use crate::user_dirs::UserDirs;
+    use crate::vars::Vars;
+    use chrono::NaiveDate;
+
+    fn paths(nodes: &[Node], prefix: &str, out: &mut Vec<String>) {
+        for node in nodes {
+            let path = format!("{prefix}{}", node.path);
+            out.push(path.clone());
+            paths(&node.children, &format!("{path}/"), out);
+        }
+    }
+
+    fn flatten(raw: &str) -> Vec<String> {
+        let mut spec: SpecFile = serde_json::from_str(raw).expect("parse");
+        spec.normalize();
+        let mut out = Vec::new();
+        paths(&spec.areas[0].required, "", &mut out);
+        out
+    }
+
+    #[test]
+    fn compact_and_nested_forms_normalize_to_the_same_tree() {
+        let compact = flatten(
+            r#"{ "version": 1, "areas": [{ "name": "S", "root": "~/S", "required": [
+                "apps", "life-os/repo", "life-os/config"
+            ] }] }"#,
+        );
+        let nested = flatten(
+            r#"{ "version": 1, "areas": [{ "name": "S", "root": "~/S", "required": [
+                { "path": "apps" },
+                { "path": "life-os", "children": [{ "path": "repo" }, { "path": "config" }] }
+            ] }] }"#,
+        );
+
+        assert_eq!(
+            compact,
+            vec!["apps", "life-os", "life-os/repo", "life-os/config"]
+        );
+        assert_eq!(compact, nested);
+    }
+
+    #[test]
+    fn normalize_dedupes_mixed_forms() {
+        let out = flatten(
+            r#"{ "version": 1, "areas": [{ "name": "S", "root": "~/S", "required": [
+                "school/admin",
+                { "path": "school", "children": ["admin", "notes"] },
+                { "path": "school/notes/", "children": ["2026"] },
+                "apps"
+            ] }] }"#,
+        );
+
+        assert_eq!(
+            out,
+            vec![
+                "school",
+                "school/admin",
+                "school/notes",
+                "school/notes/2026",
+                "apps"
+            ]
+        );
+    }
+
+    #[test]
+    fn node_rejects_other_types() {
+        let err = serde_json::from_str::<Node>("42").expect_err("number");
+        assert!(err.to_string().contains("a path string or a node object"));
+    }
+
+    fn test_vars(spec: &mut SpecFile) -> Vars {
+        Vars {
+            vars: std::mem::take(&mut spec.vars),
+            home: Some("/home/tester".into()),
+            host: None,
+            today: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+            env: |_| None,
+            user_dirs: Some(UserDirs::defaults(std::path::Path::new("/home/tester"))),
+        }
+    }
+
+    #[test]
+    fn expand_vars_resolves_roots_and_paths() {
+        let mut spec: SpecFile = serde_json::from_str(
+            r#"{ "version": 1, "vars": { "school": "school/{school_year}" }, "areas": [
+                { "name": "Docs", "root": "${XDG_DOCUMENTS_DIR}", "required": ["${school}/notes"] }
+            ] }"#,
+        )
+        .expect("parse");
+        let vars = test_vars(&mut spec);
+        expand_vars(&mut spec, &vars).expect("expand");
+
+        assert_eq!(spec.areas[0].root, "/home/tester/Documents");
+        let mut out = Vec::new();
+        paths(&spec.areas[0].required, "", &mut out);
+        assert_eq!(
+            out,
+            vec!["school", "school/2026-2027", "school/2026-2027/notes"]
+        );
+    }
+
+    #[test]
+    fn expand_vars_names_area_and_node_on_error() {
+        let mut spec: SpecFile = serde_json::from_str(
+            r#"{ "version": 1, "areas": [
+                { "name": "Docs", "root": "~/Documents", "required": [
+                    { "path": "school", "children": ["${TERM_NAME}"] }
+                ] }
+            ] }"#,
+        )
+        .expect("parse");
+        let vars = test_vars(&mut spec);
+        let err = expand_vars(&mut spec, &vars).expect_err("undefined");
+
+        assert_eq!(
+            format!("{err:#}"),
+            "in area `Docs`, node `school/${TERM_NAME}`: undefined variable `${TERM_NAME}`"
+        );
+    }
 }
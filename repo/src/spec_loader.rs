@@ -1,22 +1,351 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail, ensure};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::spec::SpecFile;
+use crate::spec::{CURRENT_VERSION, SpecFile, expand_vars, validate_globs};
+use crate::vars::Vars;
 
-pub fn load_spec() -> Result<SpecFile> {
-    let path = spec_path();
-    let raw = fs::read_to_string(&path)
+pub const SPEC_ENV_VAR: &str = "LIFE_OS_SPEC";
+pub const HOST_ENV_VAR: &str = "LIFE_OS_HOST";
+
+/// File names tried in each search directory, in order.
+const SPEC_FILE_NAMES: &[&str] = &["spec.json", "spec.toml", "spec.yaml", "spec.yml"];
+
+/// Where the spec file was found and which rule picked it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct SpecLocation {
+    pub path: PathBuf,
+    pub source: SpecSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpecSource {
+    Flag,
+    Env,
+    XdgConfig,
+    Legacy,
+    System,
+}
+
+impl fmt::Display for SpecSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SpecSource::Flag => "--spec flag",
+            SpecSource::Env => "LIFE_OS_SPEC",
+            SpecSource::XdgConfig => "XDG config dir",
+            SpecSource::Legacy => "legacy path",
+            SpecSource::System => "system config",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Resolves the spec file: `--spec`, then `LIFE_OS_SPEC`, then the first
+/// existing file in the search path.
+pub fn locate_spec(flag: Option<&Path>) -> Result<SpecLocation> {
+    let home = dirs::home_dir();
+    let xdg_config = std::env::var_os("XDG_CONFIG_HOME");
+    let candidates = search_path(home.as_deref(), xdg_config);
+    locate_spec_in(flag, std::env::var_os(SPEC_ENV_VAR), &candidates)
+}
+
+fn locate_spec_in(
+    flag: Option<&Path>,
+    env: Option<OsString>,
+    candidates: &[(PathBuf, SpecSource)],
+) -> Result<SpecLocation> {
+    if let Some(path) = flag {
+        return Ok(SpecLocation {
+            path: path.to_path_buf(),
+            source: SpecSource::Flag,
+        });
+    }
+
+    if let Some(value) = env.filter(|v| !v.is_empty()) {
+        return Ok(SpecLocation {
+            path: PathBuf::from(value),
+            source: SpecSource::Env,
+        });
+    }
+
+    for (path, source) in candidates {
+        if path.is_file() {
+            return Ok(SpecLocation {
+                path: path.clone(),
+                source: *source,
+            });
+        }
+    }
+
+    let searched = candidates
+        .iter()
+        .map(|(path, _)| format!("  {}", path.display()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    bail!("no spec file found (pass --spec <path> or set {SPEC_ENV_VAR}); searched:\n{searched}")
+}
+
+fn search_path(home: Option<&Path>, xdg_config: Option<OsString>) -> Vec<(PathBuf, SpecSource)> {
+    let mut out = Vec::new();
+
+    let config_dir = xdg_config
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|h| h.join(".config")));
+    let mut dirs = Vec::new();
+    if let Some(dir) = config_dir {
+        dirs.push((dir.join("life-os"), SpecSource::XdgConfig));
+    }
+    if let Some(home) = home {
+        dirs.push((home.join("System/life-os/config"), SpecSource::Legacy));
+    }
+    dirs.push((PathBuf::from("/etc/life-os"), SpecSource::System));
+
+    for (dir, source) in dirs {
+        for name in SPEC_FILE_NAMES {
+            out.push((dir.join(name), source));
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SpecFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl SpecFormat {
+    /// Picks the format from the file extension; no extension means JSON.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            None | Some("json") => Ok(SpecFormat::Json),
+            Some("toml") => Ok(SpecFormat::Toml),
+            Some("yaml" | "yml") => Ok(SpecFormat::Yaml),
+            Some(other) => bail!(
+                "unsupported spec extension `.{other}` (expected .json, .toml, .yaml or .yml): {}",
+                path.display()
+            ),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SpecFormat::Json => "json",
+            SpecFormat::Toml => "toml",
+            SpecFormat::Yaml => "yaml",
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, raw: &str) -> Result<T> {
+        Ok(match self {
+            SpecFormat::Json => serde_json::from_str(raw)?,
+            SpecFormat::Toml => toml::from_str(raw)?,
+            SpecFormat::Yaml => serde_norway::from_str(raw)?,
+        })
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String> {
+        let mut out = match self {
+            SpecFormat::Json => serde_json::to_string_pretty(value)?,
+            SpecFormat::Toml => toml::to_string_pretty(value)?,
+            SpecFormat::Yaml => serde_norway::to_string(value)?,
+        };
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// Upgrades a spec document from version `from` to `from + 1`.
+pub struct Migration {
+    pub from: u32,
+    pub apply: fn(&mut Value) -> Result<()>,
+}
+
+/// Every format change bumps `CURRENT_VERSION` and adds a step here.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Loads the spec with its includes and the overlay for this machine.
+pub fn load_spec(location: &SpecLocation) -> Result<SpecFile> {
+    let host = current_host();
+    let mut spec = load_spec_for_host(&location.path, host.as_deref())?;
+    let vars = Vars::new(std::mem::take(&mut spec.vars), host);
+    expand_vars(&mut spec, &vars)
+        .and_then(|_| validate_globs(&spec))
+        .with_context(|| format!("failed to load spec: {}", location.path.display()))?;
+    Ok(spec)
+}
+
+/// Short hostname (`music-laptop` for `music-laptop.local`), overridable
+/// with `LIFE_OS_HOST`.
+pub fn current_host() -> Option<String> {
+    let name = match std::env::var(HOST_ENV_VAR) {
+        Ok(value) if !value.is_empty() => value,
+        _ => gethostname::gethostname().to_string_lossy().into_owned(),
+    };
+    let short = name.split('.').next().unwrap_or_default();
+    (!short.is_empty()).then(|| short.to_string())
+}
+
+fn load_spec_for_host(path: &Path, host: Option<&str>) -> Result<SpecFile> {
+    let home = dirs::home_dir();
+    let home = home.as_deref();
+    let mut stack = Vec::new();
+    let mut spec = load_tree(path, home, &mut stack)?;
+
+    if let Some(host) = host {
+        if let Some(overlay) = spec.hosts.remove(host) {
+            spec.vars.extend(overlay.vars);
+            spec.merge_areas(overlay.areas)?;
+            for include in overlay.include {
+                let other = load_tree(&include_path(&include, home), home, &mut stack)
+                    .with_context(|| format!("included from hosts.{host} in {}", path.display()))?;
+                spec.merge(other)?;
+            }
+            spec.tidy.overlay(overlay.tidy);
+        }
+
+        let overlay_dir = path.parent().unwrap_or(Path::new("")).join("spec.d");
+        let overlay = ["json", "toml", "yaml", "yml"]
+            .iter()
+            .map(|ext| overlay_dir.join(format!("{host}.{ext}")))
+            .find(|candidate| candidate.is_file());
+        if let Some(overlay) = overlay {
+            let other = load_tree(&overlay, home, &mut stack)?;
+            spec.merge(other)?;
+        }
+    }
+
+    for area in &spec.areas {
+        ensure!(
+            !area.root.trim().is_empty(),
+            "area `{}` has no root",
+            area.name
+        );
+    }
+    Ok(spec)
+}
+
+fn load_tree(path: &Path, home: Option<&Path>, stack: &mut Vec<PathBuf>) -> Result<SpecFile> {
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&key) {
+        bail!(
+            "spec include cycle: {} is already being loaded",
+            path.display()
+        );
+    }
+
+    let mut spec = read_spec_file(path)?;
+    spec.set_source(path);
+
+    // A file's own vars and tidy settings win over the files it includes.
+    let own_vars = spec.vars.clone();
+    let own_tidy = std::mem::take(&mut spec.tidy);
+    stack.push(key);
+    for include in std::mem::take(&mut spec.include) {
+        let other = load_tree(&include_path(&include, home), home, stack)
+            .with_context(|| format!("included from {}", path.display()))?;
+        spec.merge(other)?;
+    }
+    stack.pop();
+    spec.vars.extend(own_vars);
+    spec.tidy.overlay(own_tidy);
+
+    Ok(spec)
+}
+
+fn include_path(include: &str, home: Option<&Path>) -> PathBuf {
+    match home {
+        Some(home) => expand_root(include, home),
+        None => PathBuf::from(include),
+    }
+}
+
+fn read_spec_file(path: &Path) -> Result<SpecFile> {
+    let raw = fs::read_to_string(path)
         .with_context(|| format!("failed to read spec file: {}", path.display()))?;
+    let format = SpecFormat::from_path(path)?;
+
+    parse_spec(&raw, format)
+        .with_context(|| format!("failed to parse spec file: {}", path.display()))
+}
 
-    let spec: SpecFile = serde_json::from_str(&raw).context("failed to parse spec.json")?;
+pub fn parse_spec(raw: &str, format: SpecFormat) -> Result<SpecFile> {
+    let mut doc: Value = format.parse(raw)?;
+    let original = migrate(&mut doc)?;
 
+    // Current-version files are parsed straight from text to keep error positions.
+    let mut spec: SpecFile = if original == CURRENT_VERSION {
+        format.parse(raw)?
+    } else {
+        serde_json::from_value(doc)?
+    };
+    ensure!(
+        spec.version == CURRENT_VERSION,
+        "spec version {} was not migrated to {CURRENT_VERSION}",
+        spec.version
+    );
+    spec.normalize();
     Ok(spec)
 }
 
-fn spec_path() -> PathBuf {
-    let home = dirs::home_dir().expect("home directory not found");
-    home.join("System/life-os/config/spec.json")
+/// Migrates `doc` in place to the current version and returns the version it
+/// started at.
+pub fn migrate(doc: &mut Value) -> Result<u32> {
+    migrate_with(doc, CURRENT_VERSION, MIGRATIONS)
+}
+
+fn migrate_with(doc: &mut Value, latest: u32, migrations: &[Migration]) -> Result<u32> {
+    let original = spec_version(doc)?;
+    if original > latest {
+        bail!(
+            "spec version {original} is newer than this build of life-os supports \
+             (latest: {latest}); upgrade life-os to use this spec"
+        );
+    }
+
+    let mut version = original;
+    while version < latest {
+        let step = migrations
+            .iter()
+            .find(|m| m.from == version)
+            .with_context(|| format!("no migration from spec version {version}"))?;
+        (step.apply)(doc).with_context(|| {
+            format!(
+                "failed to migrate spec from version {version} to {}",
+                version + 1
+            )
+        })?;
+        version += 1;
+        doc["version"] = Value::from(version);
+    }
+
+    Ok(original)
+}
+
+fn spec_version(doc: &Value) -> Result<u32> {
+    let value = doc
+        .get("version")
+        .context("spec is missing the `version` key")?;
+    let version = value
+        .as_u64()
+        .and_then(|v| u32::try_from(v).ok())
+        .filter(|v| *v >= 1)
+        .with_context(|| format!("spec `version` must be a positive integer, got {value}"))?;
+    Ok(version)
 }
 
 pub fn expand_root(root: &str, home: &Path) -> PathBuf {
@@ -29,8 +358,15 @@ pub fn expand_root(root: &str, home: &Path) -> PathBuf {
 
 #[cfg(test)]
 mod tests {
-    use super::expand_root;
+    use super::{
+        Migration, SpecFormat, SpecSource, expand_root, load_spec_for_host, locate_spec_in,
+        migrate_with, parse_spec, search_path,
+    };
+    use serde_json::json;
+    use std::ffi::OsString;
+    use std::fs;
     use std::path::Path;
+    use tempfile::tempdir;
 
     #[test]
     fn expand_root_expands_tilde_prefix() {
@@ -45,4 +381,253 @@ mod tests {
         let out = expand_root("/var/data", home);
         assert_eq!(out, Path::new("/var/data"));
     }
+
+    #[test]
+    fn locate_spec_prefers_flag_over_env() {
+        let flag = Path::new("/tmp/flag.json");
+        let env = Some(OsString::from("/tmp/env.json"));
+
+        let found = locate_spec_in(Some(flag), env.clone(), &[]).expect("flag");
+        assert_eq!(found.path, flag);
+        assert_eq!(found.source, SpecSource::Flag);
+
+        let found = locate_spec_in(None, env, &[]).expect("env");
+        assert_eq!(found.path, Path::new("/tmp/env.json"));
+        assert_eq!(found.source, SpecSource::Env);
+    }
+
+    #[test]
+    fn locate_spec_uses_first_existing_candidate() {
+        let dir = tempdir().expect("tempdir");
+        let home = dir.path();
+        let candidates = search_path(Some(home), None);
+
+        let legacy = home.join("System/life-os/config/spec.json");
+        fs::create_dir_all(legacy.parent().unwrap()).expect("legacy dir");
+        fs::write(&legacy, "{}").expect("write legacy");
+
+        let found = locate_spec_in(None, None, &candidates).expect("legacy");
+        assert_eq!(found.path, legacy);
+        assert_eq!(found.source, SpecSource::Legacy);
+
+        let xdg = home.join(".config/life-os/spec.json");
+        fs::create_dir_all(xdg.parent().unwrap()).expect("xdg dir");
+        fs::write(&xdg, "{}").expect("write xdg");
+
+        let found = locate_spec_in(None, None, &candidates).expect("xdg");
+        assert_eq!(found.path, xdg);
+        assert_eq!(found.source, SpecSource::XdgConfig);
+
+        fs::remove_file(&xdg).expect("remove xdg json");
+        let xdg_toml = home.join(".config/life-os/spec.toml");
+        fs::write(&xdg_toml, "").expect("write xdg toml");
+
+        let found = locate_spec_in(None, None, &candidates).expect("xdg toml");
+        assert_eq!(found.path, xdg_toml);
+    }
+
+    #[test]
+    fn locate_spec_errors_when_nothing_found() {
+        let dir = tempdir().expect("tempdir");
+        let candidates = vec![(dir.path().join("nope.json"), SpecSource::System)];
+
+        let err = locate_spec_in(None, None, &candidates).expect_err("no spec");
+        assert!(err.to_string().contains("nope.json"));
+    }
+
+    #[test]
+    fn parse_spec_rejects_versions_from_the_future() {
+        let err =
+            parse_spec(r#"{ "version": 99, "areas": [] }"#, SpecFormat::Json).expect_err("future");
+        assert!(err.to_string().contains("newer than this build"));
+    }
+
+    #[test]
+    fn migrate_runs_each_step_in_order() {
+        fn rename_folders(doc: &mut serde_json::Value) -> anyhow::Result<()> {
+            let areas = doc["folders"].take();
+            doc["areas"] = areas;
+            doc.as_object_mut().unwrap().remove("folders");
+            Ok(())
+        }
+        fn add_marker(doc: &mut serde_json::Value) -> anyhow::Result<()> {
+            doc["marker"] = json!(true);
+            Ok(())
+        }
+        let chain = [
+            Migration {
+                from: 2,
+                apply: add_marker,
+            },
+            Migration {
+                from: 1,
+                apply: rename_folders,
+            },
+        ];
+
+        let mut doc = json!({ "version": 1, "folders": [] });
+        let original = migrate_with(&mut doc, 3, &chain).expect("migrate");
+
+        assert_eq!(original, 1);
+        assert_eq!(doc, json!({ "version": 3, "areas": [], "marker": true }));
+    }
+
+    #[test]
+    fn spec_format_is_chosen_by_extension() {
+        let cases = [
+            ("spec.json", SpecFormat::Json),
+            ("spec", SpecFormat::Json),
+            ("spec.toml", SpecFormat::Toml),
+            ("spec.yaml", SpecFormat::Yaml),
+            ("spec.YML", SpecFormat::Yaml),
+        ];
+        for (name, format) in cases {
+            assert_eq!(SpecFormat::from_path(Path::new(name)).unwrap(), format);
+        }
+        assert!(SpecFormat::from_path(Path::new("spec.ini")).is_err());
+    }
+
+    #[test]
+    fn parse_spec_reads_toml_and_yaml_into_same_model() {
+        let toml = r#"
+version = 1
+
+[[areas]]
+name = "System"
+root = "~/System"
+
+# Folders managed by life-os itself
+[[areas.required]]
+path = "life-os"
+children = [{ path = "repo" }, { path = "config" }]
+"#;
+        let yaml = r#"
+version: 1
+areas:
+  - name: System
+    root: ~/System
+    required:
+      # Folders managed by life-os itself
+      - path: life-os
+        children:
+          - path: repo
+          - path: config
+"#;
+        for spec in [
+            parse_spec(toml, SpecFormat::Toml).expect("toml"),
+            parse_spec(yaml, SpecFormat::Yaml).expect("yaml"),
+        ] {
+            assert_eq!(spec.areas[0].root, "~/System");
+            assert_eq!(spec.areas[0].required[0].path, "life-os");
+            assert_eq!(spec.areas[0].required[0].children[1].path, "config");
+        }
+    }
+
+    #[test]
+    fn load_merges_includes_and_host_overlays() {
+        let dir = tempdir().expect("tempdir");
+        let base = dir.path().join("spec.json");
+        let common = dir.path().join("common.json");
+        let overlay = dir.path().join("spec.d/music.toml");
+
+        fs::write(
+            &base,
+            r#"{
+  "version": 1,
+  "include": ["common.json"],
+  "hosts": {
+    "music": { "areas": [{ "name": "Documents", "required": ["audio"] }] }
+  },
+  "tidy": {
+    "desktop": { "screenshot_globs": ["A*.png"] },
+    "downloads": { "older_than": "14d" },
+    "inboxes": [{ "name": "Scans", "path": "/scans", "rules": [{ "action": "keep" }] }]
+  },
+  "areas": [{ "name": "Documents", "root": "/docs", "required": ["files"] }]
+}"#,
+        )
+        .expect("write base");
+        fs::write(
+            &common,
+            r#"{
+  "version": 1,
+  "tidy": {
+    "desktop": { "screenshot_globs": ["B*.png"], "screenshot_regexes": ["^Shot"] },
+    "downloads": { "older_than": "30d", "many_items": 50 },
+    "inboxes": [{ "name": "scans", "rules": [{ "action": "delete" }] }]
+  },
+  "areas": [
+    { "name": "Documents", "required": ["files/pdf", "legal"] },
+    { "name": "System", "root": "/sys", "required": ["apps"] }
+  ]
+}"#,
+        )
+        .expect("write common");
+        fs::create_dir_all(overlay.parent().unwrap()).expect("spec.d");
+        fs::write(
+            &overlay,
+            r#"
+version = 1
+
+[tidy.downloads]
+older_than = "3d"
+
+[[areas]]
+name = "Documents"
+required = ["Image-Line"]
+"#,
+        )
+        .expect("write overlay");
+
+        let spec = load_spec_for_host(&base, Some("music")).expect("load");
+        let docs = &spec.areas[0];
+        let paths: Vec<_> = docs.required.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, vec!["files", "legal", "audio", "Image-Line"]);
+        assert_eq!(docs.required[0].source.as_deref(), Some(base.as_path()));
+        assert_eq!(
+            docs.required[0].children[0].source.as_deref(),
+            Some(common.as_path())
+        );
+        assert_eq!(docs.required[3].source.as_deref(), Some(overlay.as_path()));
+        assert_eq!(spec.areas[1].name, "System");
+        // Own settings beat includes; the machine overlay beats both.
+        assert_eq!(spec.tidy.downloads.older_than.as_deref(), Some("3d"));
+        assert_eq!(spec.tidy.downloads.many_items, Some(50));
+        assert_eq!(spec.tidy.inboxes.len(), 1);
+        let scans = &spec.tidy.inboxes[0];
+        let actions: Vec<_> = scans.rules.iter().map(|r| r.action.as_str()).collect();
+        assert_eq!(actions, ["delete", "keep"]);
+        assert_eq!(spec.tidy.desktop.screenshot_globs, ["B*.png", "A*.png"]);
+        assert_eq!(spec.tidy.desktop.screenshot_regexes, ["^Shot"]);
+
+        let other = load_spec_for_host(&base, Some("laptop")).expect("load other host");
+        assert_eq!(other.areas[0].required.len(), 2);
+        assert_eq!(other.tidy.downloads.older_than.as_deref(), Some("14d"));
+    }
+
+    #[test]
+    fn load_rejects_include_cycles_and_conflicting_roots() {
+        let dir = tempdir().expect("tempdir");
+        let a = dir.path().join("a.json");
+        let b = dir.path().join("b.json");
+        fs::write(&a, r#"{ "version": 1, "include": ["b.json"] }"#).expect("write a");
+        fs::write(&b, r#"{ "version": 1, "include": ["a.json"] }"#).expect("write b");
+
+        let err = load_spec_for_host(&a, None).expect_err("cycle");
+        assert!(format!("{err:#}").contains("cycle"));
+
+        fs::write(
+            &b,
+            r#"{ "version": 1, "areas": [{ "name": "S", "root": "/other" }] }"#,
+        )
+        .expect("rewrite b");
+        fs::write(
+            &a,
+            r#"{ "version": 1, "include": ["b.json"], "areas": [{ "name": "S", "root": "/sys" }] }"#,
+        )
+        .expect("rewrite a");
+
+        let err = load_spec_for_host(&a, None).expect_err("conflict");
+        assert!(format!("{err:#}").contains("conflicting roots"));
+    }
 }
@@ -0,0 +1,260 @@
+//! Moving items between folders that may sit on different filesystems.
+//! `rename` cannot cross mounts (EXDEV); then the item is copied, synced and
+//! compared with the original (sizes and SHA-256) before the original is
+//! removed. If anything goes wrong the original stays and the partial copy
+//! is cleaned up.
+
+use anyhow::{Context, Result, bail};
+use std::fs::{self, File, FileTimes, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::hash::file_hash;
+use crate::trash::delete_permanently;
+
+/// Moves `from` to `to`, which must not exist yet.
+pub fn move_path(from: &Path, to: &Path) -> Result<()> {
+    move_with(from, to, |from, to| fs::rename(from, to))
+}
+
+fn move_with(
+    from: &Path,
+    to: &Path,
+    rename: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> Result<()> {
+    match rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(err) if crosses_devices(&err) => copy_and_remove(from, to),
+        Err(err) => Err(err)
+            .with_context(|| format!("failed to move {} -> {}", from.display(), to.display())),
+    }
+}
+
+/// `rename` failed because `from` and `to` are on different filesystems.
+fn crosses_devices(err: &io::Error) -> bool {
+    err.kind() == ErrorKind::CrossesDevices
+}
+
+fn copy_and_remove(from: &Path, to: &Path) -> Result<()> {
+    if fs::symlink_metadata(to).is_ok() {
+        bail!("cannot move {}: {} exists", from.display(), to.display());
+    }
+    let mut folders = Vec::new();
+    let copied = copy_tree(from, to, &mut folders)
+        .with_context(|| format!("failed to copy {} -> {}", from.display(), to.display()))
+        .and_then(|()| verify(from, to))
+        .and_then(|()| {
+            // Deepest first, so a folder's own permissions cannot block its
+            // children.
+            for (folder, permissions) in folders {
+                fs::set_permissions(&folder, permissions).with_context(|| {
+                    format!("failed to set permissions on {}", folder.display())
+                })?;
+            }
+            Ok(())
+        });
+    if let Err(err) = copied {
+        if fs::symlink_metadata(to).is_ok()
+            && let Err(cleanup) = delete_permanently(to)
+        {
+            return Err(err.context(format!(
+                "the partial copy at {} could not be removed: {cleanup:#}",
+                to.display()
+            )));
+        }
+        return Err(err);
+    }
+    if let Some(parent) = to.parent() {
+        sync_dir(parent);
+    }
+    delete_permanently(from).with_context(|| {
+        format!(
+            "copied to {} but could not remove the original",
+            to.display()
+        )
+    })
+}
+
+/// Folder permissions are collected in `folders` instead of applied: a
+/// read-only copy could not be cleaned up if verifying it fails.
+fn copy_tree(
+    from: &Path,
+    to: &Path,
+    folders: &mut Vec<(PathBuf, fs::Permissions)>,
+) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    let file_type = meta.file_type();
+    if file_type.is_symlink() {
+        return copy_symlink(from, to);
+    }
+    if file_type.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()), folders)?;
+        }
+    } else if file_type.is_file() {
+        let mut source = File::open(from)?;
+        let mut target = OpenOptions::new().write(true).create_new(true).open(to)?;
+        io::copy(&mut source, &mut target)?;
+        target.sync_all()?;
+    } else {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!("cannot copy special file {}", from.display()),
+        ));
+    }
+    // Last, so copying a folder's children does not bump its mtime again;
+    // times before permissions, which may not let us open `to` any more.
+    let mut times = FileTimes::new().set_modified(meta.modified()?);
+    if let Ok(accessed) = meta.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    File::open(to)?.set_times(times)?;
+    if file_type.is_dir() {
+        folders.push((to.to_path_buf(), meta.permissions()));
+        Ok(())
+    } else {
+        fs::set_permissions(to, meta.permissions())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        format!("cannot copy symlink {}", from.display()),
+    ))
+}
+
+/// Same tree shape, same symlink targets, same file sizes and hashes.
+fn verify(from: &Path, to: &Path) -> Result<()> {
+    let mismatch = || format!("copy of {} does not match the original", from.display());
+    let (a, b) = (fs::symlink_metadata(from)?, fs::symlink_metadata(to)?);
+    if a.file_type().is_symlink() {
+        if !b.file_type().is_symlink() || fs::read_link(from)? != fs::read_link(to)? {
+            bail!(mismatch());
+        }
+    } else if a.is_dir() {
+        let names = |dir: &Path| -> io::Result<Vec<_>> {
+            let mut names = fs::read_dir(dir)?
+                .map(|e| e.map(|e| e.file_name()))
+                .collect::<io::Result<Vec<_>>>()?;
+            names.sort();
+            Ok(names)
+        };
+        let children = names(from)?;
+        if !b.is_dir() || children != names(to)? {
+            bail!(mismatch());
+        }
+        for name in children {
+            verify(&from.join(&name), &to.join(&name))?;
+        }
+    } else if !b.is_file() || a.len() != b.len() || file_hash(from)? != file_hash(to)? {
+        bail!(mismatch());
+    }
+    Ok(())
+}
+
+/// Persists the new directory entry; best effort.
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{copy_and_remove, move_with};
+    use filetime::{FileTime, set_file_mtime};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn copy_and_remove_keeps_contents_times_and_permissions() {
+        let dir = tempdir().expect("tempdir");
+        let from = dir.path().join("album");
+        fs::create_dir_all(from.join("disc 1")).expect("dirs");
+        fs::write(from.join("disc 1/track.flac"), "music").expect("write");
+        fs::set_permissions(
+            from.join("disc 1/track.flac"),
+            fs::Permissions::from_mode(0o640),
+        )
+        .expect("chmod");
+        std::os::unix::fs::symlink("disc 1/track.flac", from.join("latest")).expect("symlink");
+        let mtime = FileTime::from_unix_time(1_689_422_400, 0);
+        set_file_mtime(from.join("disc 1/track.flac"), mtime).expect("mtime");
+        set_file_mtime(from.join("disc 1"), mtime).expect("mtime");
+        fs::set_permissions(from.join("disc 1"), fs::Permissions::from_mode(0o555)).expect("chmod");
+
+        let to = dir.path().join("moved");
+        copy_and_remove(&from, &to).expect("move");
+
+        assert!(!from.exists());
+        let track = to.join("disc 1/track.flac");
+        assert_eq!(fs::read_to_string(&track).unwrap(), "music");
+        let meta = fs::metadata(&track).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o640);
+        assert_eq!(FileTime::from_last_modification_time(&meta), mtime);
+        let folder = fs::metadata(to.join("disc 1")).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&folder), mtime);
+        assert_eq!(folder.permissions().mode() & 0o777, 0o555);
+        fs::set_permissions(to.join("disc 1"), fs::Permissions::from_mode(0o755)).expect("chmod");
+        assert_eq!(
+            fs::read_link(to.join("latest")).unwrap(),
+            std::path::Path::new("disc 1/track.flac")
+        );
+    }
+
+    #[test]
+    fn move_falls_back_to_copying_only_across_filesystems() {
+        let dir = tempdir().expect("tempdir");
+        let from = dir.path().join("report.pdf");
+        let to = dir.path().join("moved.pdf");
+        fs::write(&from, "pdf").expect("write");
+
+        let denied = |_: &Path, _: &Path| Err(std::io::ErrorKind::PermissionDenied.into());
+        assert!(move_with(&from, &to, denied).is_err());
+        assert!(from.exists() && !to.exists());
+
+        fs::set_permissions(&from, fs::Permissions::from_mode(0o400)).expect("chmod");
+        let exdev = |_: &Path, _: &Path| Err(std::io::Error::from_raw_os_error(libc::EXDEV));
+        move_with(&from, &to, exdev).expect("copy fallback");
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "pdf");
+        let mode = fs::metadata(&to).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o400);
+    }
+
+    #[test]
+    fn copy_and_remove_leaves_the_source_when_copying_fails() {
+        let dir = tempdir().expect("tempdir");
+        let from = dir.path().join("inbox");
+        fs::create_dir_all(&from).expect("dir");
+        fs::write(from.join("a.txt"), "a").expect("write");
+        let fifo = std::ffi::CString::new(from.join("pipe").to_str().unwrap()).unwrap();
+        // SAFETY: `fifo` is a valid NUL-terminated path.
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        // The partial copy must still be removable.
+        fs::set_permissions(&from, fs::Permissions::from_mode(0o555)).expect("chmod");
+
+        let to = dir.path().join("moved");
+        let err = copy_and_remove(&from, &to).unwrap_err();
+        fs::set_permissions(&from, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        assert!(format!("{err:#}").contains("special file"));
+        assert!(from.join("a.txt").exists());
+        assert!(!to.exists());
+
+        fs::write(dir.path().join("taken"), "x").expect("write");
+        assert!(copy_and_remove(&from.join("a.txt"), &dir.path().join("taken")).is_err());
+        assert_eq!(fs::read_to_string(dir.path().join("taken")).unwrap(), "x");
+    }
+}
@@ -0,0 +1,245 @@
+//! freedesktop.org Trash (https://specifications.freedesktop.org/trash-spec/).
+//!
+//! Items on the same filesystem as the home trash go to
+//! `$XDG_DATA_HOME/Trash`; items on other mounts go to `$topdir/.Trash/$uid`
+//! when that is a sticky, non-symlink directory, else `$topdir/.Trash-$uid`.
+//! Each item gets a `info/<name>.trashinfo` next to `files/<name>` so file
+//! managers can restore it.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::disk_usage;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct TrashedItem {
+    pub original: PathBuf,
+    /// The item inside `<trash>/files`.
+    pub stored: PathBuf,
+    /// Its `<trash>/info/<name>.trashinfo`.
+    pub info: PathBuf,
+    pub size: u64,
+}
+
+/// `$XDG_DATA_HOME/Trash`, defaulting to `~/.local/share/Trash`.
+pub fn home_trash(home: &Path) -> PathBuf {
+    match std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(data) => PathBuf::from(data).join("Trash"),
+        None => home.join(".local/share/Trash"),
+    }
+}
+
+/// Moves `path` to the trash that belongs to its filesystem.
+pub fn trash(path: &Path, home_trash: &Path, now: DateTime<Local>) -> Result<TrashedItem> {
+    let path = std::path::absolute(path)
+        .with_context(|| format!("cannot resolve path: {}", path.display()))?;
+    let (trash_dir, topdir) = trash_dir_for(&path, home_trash)?;
+    // Home trash records absolute paths; per-mount trashes record paths
+    // relative to the mount so the disk can be mounted elsewhere.
+    let recorded = match &topdir {
+        Some(top) => path.strip_prefix(top).unwrap_or(&path).to_path_buf(),
+        None => path.clone(),
+    };
+
+    let files = trash_dir.join("files");
+    let info = trash_dir.join("info");
+    for dir in [&files, &info] {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create trash directory: {}", dir.display()))?;
+    }
+
+    let name = path
+        .file_name()
+        .with_context(|| format!("cannot trash {}", path.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let size = disk_usage::size(&path);
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(&recorded),
+        now.format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    // The .trashinfo file is created exclusively first; that claims the name.
+    for attempt in 0u32.. {
+        let candidate = if attempt == 0 {
+            name.clone()
+        } else {
+            format!("{name}.{attempt}")
+        };
+        let info_path = info.join(format!("{candidate}.trashinfo"));
+        let stored = files.join(&candidate);
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to create trash info: {}", info_path.display())
+                });
+            }
+        };
+        if fs::symlink_metadata(&stored).is_ok() {
+            // Leftover without info file; keep looking but leave it alone.
+            drop(file);
+            let _ = fs::remove_file(&info_path);
+            continue;
+        }
+        file.write_all(contents.as_bytes())
+            .with_context(|| format!("failed to write trash info: {}", info_path.display()))?;
+
+        if let Err(err) = fs::rename(&path, &stored) {
+            let _ = fs::remove_file(&info_path);
+            return Err(err).with_context(|| {
+                format!("failed to trash {} -> {}", path.display(), stored.display())
+            });
+        }
+        return Ok(TrashedItem {
+            original: path,
+            stored,
+            info: info_path,
+            size,
+        });
+    }
+    unreachable!("unbounded name search")
+}
+
+/// The `.trashinfo` belonging to an item in `<trash>/files`.
+pub fn info_path_for(stored: &Path) -> Option<PathBuf> {
+    let name = stored.file_name()?.to_str()?;
+    let trash_dir = stored.parent()?.parent()?;
+    Some(trash_dir.join("info").join(format!("{name}.trashinfo")))
+}
+
+#[cfg(unix)]
+fn trash_dir_for(path: &Path, home_trash: &Path) -> Result<(PathBuf, Option<PathBuf>)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = fs::symlink_metadata(path)
+        .with_context(|| format!("cannot trash {}", path.display()))?
+        .dev();
+    let home_device = home_trash
+        .ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .map(|meta| meta.dev());
+    if home_device == Some(device) {
+        return Ok((home_trash.to_path_buf(), None));
+    }
+
+    // Walk up to the mount point: the last ancestor on the same device.
+    let mut topdir = path.parent().unwrap_or(path).to_path_buf();
+    while let Some(parent) = topdir.parent() {
+        match fs::metadata(parent) {
+            Ok(meta) if meta.dev() == device => topdir = parent.to_path_buf(),
+            _ => break,
+        }
+    }
+
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    let shared = topdir.join(".Trash");
+    let usable_shared = fs::symlink_metadata(&shared)
+        .map(|meta| meta.is_dir() && meta.mode() & 0o1000 != 0)
+        .unwrap_or(false);
+    let dir = if usable_shared {
+        shared.join(uid.to_string())
+    } else {
+        topdir.join(format!(".Trash-{uid}"))
+    };
+    if fs::symlink_metadata(&dir).is_err() {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("failed to create trash: {}", dir.display()))?;
+    }
+    Ok((dir, Some(topdir)))
+}
+
+#[cfg(not(unix))]
+fn trash_dir_for(_path: &Path, _home_trash: &Path) -> Result<(PathBuf, Option<PathBuf>)> {
+    anyhow::bail!("the freedesktop trash is only supported on Unix systems")
+}
+
+/// Percent-encodes a path for the `Path=` key (RFC 2396, `/` kept).
+fn encode_path(path: &Path) -> String {
+    let raw = path.as_os_str().as_encoded_bytes();
+    let mut out = String::with_capacity(raw.len());
+    for &byte in raw {
+        if byte.is_ascii_alphanumeric() || b"/-_.!~*'()".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// Removes `path` for good.
+pub fn delete_permanently(path: &Path) -> Result<()> {
+    let meta = fs::symlink_metadata(path)
+        .with_context(|| format!("failed to delete: {}", path.display()))?;
+    if meta.is_dir() {
+        fs::remove_dir_all(path)
+            .with_context(|| format!("failed to delete dir: {}", path.display()))
+    } else {
+        fs::remove_file(path).with_context(|| format!("failed to delete file: {}", path.display()))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{encode_path, info_path_for, trash};
+    use chrono::{Local, TimeZone};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn encode_path_escapes_reserved_bytes() {
+        assert_eq!(
+            encode_path(Path::new("/home/u/My File #1.pdf")),
+            "/home/u/My%20File%20%231.pdf"
+        );
+    }
+
+    #[test]
+    fn trash_writes_trashinfo_and_avoids_name_clashes() {
+        let dir = tempdir().expect("tempdir");
+        let trash_dir = dir.path().join("data/Trash");
+        let downloads = dir.path().join("Downloads");
+        fs::create_dir_all(&downloads).expect("downloads");
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 10, 15, 0).unwrap();
+
+        fs::write(downloads.join("a b.txt"), "one").expect("file");
+        let first = trash(&downloads.join("a b.txt"), &trash_dir, now).expect("trash");
+        fs::write(downloads.join("a b.txt"), "two").expect("file");
+        let second = trash(&downloads.join("a b.txt"), &trash_dir, now).expect("trash again");
+
+        assert_eq!(first.stored, trash_dir.join("files/a b.txt"));
+        assert_eq!(second.stored, trash_dir.join("files/a b.txt.1"));
+        assert_eq!(first.size, 3);
+        assert!(!downloads.join("a b.txt").exists());
+        assert_eq!(info_path_for(&second.stored), Some(second.info.clone()));
+
+        let info = fs::read_to_string(&first.info).expect("trashinfo");
+        let expected_path = downloads
+            .join("a b.txt")
+            .display()
+            .to_string()
+            .replace(' ', "%20");
+        assert_eq!(
+            info,
+            format!("[Trash Info]\nPath={expected_path}\nDeletionDate=2026-10-16T10:15:00\n")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! XDG user directories, so the Desktop and Downloads inboxes follow
+//! localized or customized folders (`~/Työpöytä`, `XDG_DOWNLOAD_DIR="$HOME/inbox"`).
+//! An environment variable wins over `user-dirs.dirs`, which wins over the
+//! English default under `$HOME`.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Variable, default folder under `$HOME`, and `{token}` for the spec.
+pub const USER_DIRS: &[(&str, &str, &str)] = &[
+    ("XDG_DESKTOP_DIR", "Desktop", "desktop"),
+    ("XDG_DOWNLOAD_DIR", "Downloads", "downloads"),
+    ("XDG_DOCUMENTS_DIR", "Documents", "documents"),
+    ("XDG_MUSIC_DIR", "Music", "music"),
+    ("XDG_PICTURES_DIR", "Pictures", "pictures"),
+    ("XDG_VIDEOS_DIR", "Videos", "videos"),
+    ("XDG_TEMPLATES_DIR", "Templates", "templates"),
+    ("XDG_PUBLICSHARE_DIR", "Public", "public"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    Environment,
+    UserDirsFile,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Environment => "environment",
+            Source::UserDirsFile => "user-dirs.dirs",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserDirs {
+    home: PathBuf,
+    configured: BTreeMap<String, (PathBuf, Source)>,
+}
+
+impl UserDirs {
+    /// The English defaults, ignoring the environment and `user-dirs.dirs`.
+    pub fn defaults(home: &Path) -> Self {
+        Self {
+            home: home.to_path_buf(),
+            configured: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the XDG variables and `$XDG_CONFIG_HOME/user-dirs.dirs`.
+    pub fn load(home: &Path) -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let config = env("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home.join(".config"));
+        let file = fs::read_to_string(config.join("user-dirs.dirs")).ok();
+        Self::resolve(home, file.as_deref(), env)
+    }
+
+    pub fn resolve(home: &Path, file: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Self {
+        let mut dirs = Self::defaults(home);
+        for (key, path) in file.map(|file| parse(file, home)).unwrap_or_default() {
+            dirs.configured.insert(key, (path, Source::UserDirsFile));
+        }
+        for (key, _, _) in USER_DIRS {
+            if let Some(value) = env(key).and_then(|value| expand_home(&value, home)) {
+                dirs.configured
+                    .insert(key.to_string(), (value, Source::Environment));
+            }
+        }
+        // Pointing a folder at `$HOME` disables it; use the default instead.
+        dirs.configured.retain(|_, (path, _)| path != home);
+        dirs
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// The folder for an `XDG_*_DIR` variable.
+    pub fn get(&self, key: &str) -> Option<PathBuf> {
+        if let Some((path, _)) = self.configured.get(key) {
+            return Some(path.clone());
+        }
+        let (_, default, _) = USER_DIRS.iter().find(|(name, _, _)| *name == key)?;
+        Some(self.home.join(default))
+    }
+
+    /// Where the folder came from, if not the default.
+    pub fn configured(&self, key: &str) -> Option<&(PathBuf, Source)> {
+        self.configured.get(key)
+    }
+
+    pub fn desktop(&self) -> PathBuf {
+        self.get("XDG_DESKTOP_DIR").expect("known user dir")
+    }
+
+    pub fn downloads(&self) -> PathBuf {
+        self.get("XDG_DOWNLOAD_DIR").expect("known user dir")
+    }
+
+    pub fn documents(&self) -> PathBuf {
+        self.get("XDG_DOCUMENTS_DIR").expect("known user dir")
+    }
+}
+
+/// `XDG_<NAME>_DIR` variable for a `{token}` like `downloads`.
+pub fn token_key(token: &str) -> Option<&'static str> {
+    USER_DIRS
+        .iter()
+        .find(|(_, _, name)| *name == token)
+        .map(|(key, _, _)| *key)
+}
+
+/// Parses the shell-style `XDG_DESKTOP_DIR="$HOME/Desktop"` lines that
+/// `xdg-user-dirs-update` writes. Values must be absolute or start with `$HOME`.
+fn parse(contents: &str, home: &Path) -> BTreeMap<String, PathBuf> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            USER_DIRS.iter().find(|(name, _, _)| *name == key)?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            Some((key.to_string(), expand_home(value, home)?))
+        })
+        .collect()
+}
+
+fn expand_home(value: &str, home: &Path) -> Option<PathBuf> {
+    let rest = value
+        .strip_prefix("$HOME")
+        .or_else(|| value.strip_prefix("${HOME}"));
+    match rest {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            Some(home.join(rest.trim_start_matches('/')))
+        }
+        Some(_) => None,
+        None => Some(PathBuf::from(value)).filter(|path| path.is_absolute()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Source, UserDirs};
+    use std::path::Path;
+
+    #[test]
+    fn env_beats_user_dirs_file_beats_defaults() {
+        let file = r#"
+# This file is written by xdg-user-dirs-update
+XDG_DESKTOP_DIR="$HOME/Työpöytä"
+XDG_DOWNLOAD_DIR="$HOME/Lataukset"
+XDG_MUSIC_DIR="/mnt/music"
+XDG_TEMPLATES_DIR="$HOME/"
+XDG_VIDEOS_DIR="relative/videos"
+"#;
+        let home = Path::new("/home/u");
+        let dirs = UserDirs::resolve(home, Some(file), |name| {
+            (name == "XDG_DOWNLOAD_DIR").then(|| "$HOME/inbox".to_string())
+        });
+
+        assert_eq!(dirs.desktop(), home.join("Työpöytä"));
+        assert_eq!(dirs.downloads(), home.join("inbox"));
+        assert_eq!(dirs.documents(), home.join("Documents"));
+        assert_eq!(dirs.get("XDG_MUSIC_DIR").unwrap(), Path::new("/mnt/music"));
+        assert_eq!(
+            dirs.get("XDG_TEMPLATES_DIR").unwrap(),
+            home.join("Templates")
+        );
+        assert_eq!(dirs.get("XDG_VIDEOS_DIR").unwrap(), home.join("Videos"));
+        assert_eq!(
+            dirs.configured("XDG_DOWNLOAD_DIR").unwrap().1,
+            Source::Environment
+        );
+        assert_eq!(
+            dirs.configured("XDG_DESKTOP_DIR").unwrap().1,
+            Source::UserDirsFile
+        );
+        assert!(dirs.configured("XDG_DOCUMENTS_DIR").is_none());
+        assert!(dirs.get("XDG_NOPE_DIR").is_none());
+    }
+}
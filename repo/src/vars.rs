@@ -0,0 +1,220 @@
+use anyhow::{Result, bail};
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::user_dirs::{UserDirs, token_key};
+
+/// Values available to `${NAME}` and `{token}` references in the spec.
+///
+/// `${NAME}` looks at spec `vars` first, then `HOME`/`USER`/`HOSTNAME`, then
+/// the process environment. XDG user dirs resolve as [`UserDirs`] does, so
+/// `$HOME` in a variable is expanded and a relative value falls back to
+/// `user-dirs.dirs`, then `$HOME/<Name>`. `{year}`, `{month}`, `{day}` and `{school_year}`
+/// come from today's date; `{desktop}`, `{downloads}`, `{documents}` and the
+/// other user dirs are the matching `XDG_*_DIR`.
+pub struct Vars {
+    pub vars: BTreeMap<String, String>,
+    pub home: Option<PathBuf>,
+    pub host: Option<String>,
+    pub today: NaiveDate,
+    pub env: fn(&str) -> Option<String>,
+    pub user_dirs: Option<UserDirs>,
+}
+
+impl Vars {
+    pub fn new(vars: BTreeMap<String, String>, host: Option<String>) -> Self {
+        let home = dirs::home_dir();
+        Self {
+            vars,
+            user_dirs: home.as_deref().map(UserDirs::load),
+            home,
+            host,
+            today: chrono::Local::now().date_naive(),
+            env: |name| std::env::var(name).ok().filter(|v| !v.is_empty()),
+        }
+    }
+
+    /// Expands every reference in `input`. `$$`, `{{` and `}}` are literal.
+    pub fn expand(&self, input: &str) -> Result<String> {
+        self.expand_depth(input, 0)
+    }
+
+    fn expand_depth(&self, input: &str, depth: usize) -> Result<String> {
+        if depth > 16 {
+            bail!("variables reference each other in a cycle: `{input}`");
+        }
+
+        let mut out = String::new();
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('$', Some('$')) | ('{', Some('{')) | ('}', Some('}')) => {
+                    out.push(c);
+                    chars.next();
+                }
+                ('$', Some('{')) => {
+                    chars.next();
+                    let name = take_until_close(&mut chars, input)?;
+                    // Spec vars may reference other vars; outside values are literal.
+                    if let Some(value) = self.vars.get(&name) {
+                        out.push_str(&self.expand_depth(value, depth + 1)?);
+                    } else {
+                        let value = self
+                            .lookup(&name)
+                            .ok_or_else(|| anyhow::anyhow!("undefined variable `${{{name}}}`"))?;
+                        out.push_str(&value);
+                    }
+                }
+                ('{', _) => {
+                    let token = take_until_close(&mut chars, input)?;
+                    let value = self
+                        .token(&token)
+                        .ok_or_else(|| anyhow::anyhow!("undefined variable `{{{token}}}`"))?;
+                    out.push_str(&value);
+                }
+                _ => out.push(c),
+            }
+        }
+        Ok(out)
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        match name {
+            "HOME" => return self.home_string(),
+            "HOSTNAME" => return self.host.clone(),
+            "USER" => return (self.env)("USER").or_else(|| (self.env)("LOGNAME")),
+            _ => {}
+        }
+        if let Some(dirs) = &self.user_dirs
+            && let Some(path) = dirs.get(name)
+        {
+            return Some(path.to_string_lossy().into_owned());
+        }
+        (self.env)(name)
+    }
+
+    fn home_string(&self) -> Option<String> {
+        self.home.as_ref().map(|h| h.to_string_lossy().into_owned())
+    }
+
+    fn token(&self, token: &str) -> Option<String> {
+        let today = self.today;
+        Some(match token {
+            "year" => format!("{:04}", today.year()),
+            "month" => format!("{:02}", today.month()),
+            "day" => format!("{:02}", today.day()),
+            "school_year" => {
+                // School years start in August: Oct 2026 is "2026-2027".
+                let start = if today.month() >= 8 {
+                    today.year()
+                } else {
+                    today.year() - 1
+                };
+                format!("{}-{}", start, start + 1)
+            }
+            _ => return self.lookup(token_key(token)?),
+        })
+    }
+}
+
+fn take_until_close(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    input: &str,
+) -> Result<String> {
+    let mut name = String::new();
+    for c in chars.by_ref() {
+        if c == '}' {
+            return Ok(name);
+        }
+        name.push(c);
+    }
+    bail!("unterminated variable reference in `{input}`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vars;
+    use crate::user_dirs::UserDirs;
+    use chrono::NaiveDate;
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+
+    fn vars(user: &[(&str, &str)], month: u32) -> Vars {
+        Vars {
+            vars: user
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            home: Some(PathBuf::from("/home/tester")),
+            host: Some("lab".to_string()),
+            today: NaiveDate::from_ymd_opt(2026, month, 16).unwrap(),
+            env,
+            user_dirs: Some(UserDirs::resolve(
+                Path::new("/home/tester"),
+                Some(r#"XDG_DESKTOP_DIR="$HOME/Työpöytä""#),
+                env,
+            )),
+        }
+    }
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "USER" => Some("tester".to_string()),
+            "XDG_DOWNLOAD_DIR" => Some("$HOME/inbox".to_string()),
+            "XDG_VIDEOS_DIR" => Some("relative/videos".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand_resolves_builtins_env_and_user_vars() {
+        let v = vars(&[("school", "${HOME}/Documents/school")], 10);
+
+        assert_eq!(
+            v.expand("${school}/{year}").unwrap(),
+            "/home/tester/Documents/school/2026"
+        );
+        assert_eq!(v.expand("${USER}@${HOSTNAME}").unwrap(), "tester@lab");
+        assert_eq!(
+            v.expand("${XDG_DOWNLOAD_DIR}").unwrap(),
+            "/home/tester/inbox"
+        );
+        assert_eq!(
+            v.expand("${XDG_DOCUMENTS_DIR}").unwrap(),
+            "/home/tester/Documents"
+        );
+        // Not the raw environment value.
+        assert_eq!(
+            v.expand("${XDG_VIDEOS_DIR}").unwrap(),
+            "/home/tester/Videos"
+        );
+        assert_eq!(
+            v.expand("{desktop}|{downloads}").unwrap(),
+            "/home/tester/Työpöytä|/home/tester/inbox"
+        );
+        assert_eq!(v.expand("$$HOME {{y}}").unwrap(), "$HOME {y}");
+    }
+
+    #[test]
+    fn expand_handles_date_tokens() {
+        assert_eq!(
+            vars(&[], 10).expand("{year}-{month}-{day}").unwrap(),
+            "2026-10-16"
+        );
+        assert_eq!(vars(&[], 10).expand("{school_year}").unwrap(), "2026-2027");
+        assert_eq!(vars(&[], 3).expand("{school_year}").unwrap(), "2025-2026");
+    }
+
+    #[test]
+    fn expand_rejects_undefined_and_cyclic_references() {
+        let v = vars(&[("a", "${b}"), ("b", "${a}")], 10);
+
+        let err = v.expand("${NOPE}").unwrap_err();
+        assert_eq!(err.to_string(), "undefined variable `${NOPE}`");
+        let err = v.expand("{week}").unwrap_err();
+        assert_eq!(err.to_string(), "undefined variable `{week}`");
+        assert!(v.expand("${a}").unwrap_err().to_string().contains("cycle"));
+        assert!(v.expand("${HOME").is_err());
+    }
+}